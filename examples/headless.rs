@@ -0,0 +1,68 @@
+use gl_pipelines::{
+    Bindings, Buffer, BufferLayout, BufferType, Context, PassAction, Pipeline, Shader, ShaderMeta,
+    UniformBlockLayout, VertexAttribute, VertexFormat
+};
+
+const WIDTH: i32 = 64;
+const HEIGHT: i32 = 64;
+
+fn main() {
+    let mut ctx = Context::new_headless(WIDTH, HEIGHT)
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    // Fullscreen triangle, no index buffer needed
+    let vertices: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+    let vertex_buffer = Buffer::immutable(&mut ctx, BufferType::VertexBuffer, &vertices);
+
+    let bindings = Bindings {
+        vertex_buffers: vec![vertex_buffer],
+        index_buffer: None,
+        images: vec![],
+        uniform_buffers: vec![],
+    };
+
+    let shader = Shader::new(
+        &mut ctx,
+        shader::VERTEX,
+        shader::FRAGMENT,
+        ShaderMeta {
+            images: vec![],
+            uniforms: UniformBlockLayout { uniforms: vec![] },
+            uniform_blocks: vec![],
+        }
+    ).unwrap();
+
+    let pipeline = Pipeline::new(
+        &mut ctx,
+        &[BufferLayout::default()],
+        &[VertexAttribute::new("pos", VertexFormat::Float2)],
+        shader,
+    );
+
+    ctx.begin_default_pass(PassAction::clear_color(0.0, 0.0, 1.0, 1.0));
+    ctx.apply_pipeline(&pipeline);
+    ctx.apply_bindings(&bindings);
+    ctx.draw_arrays(0, 3, 1);
+    ctx.end_render_pass();
+    ctx.commit_frame();
+
+    let texture = ctx.default_framebuffer_texture().unwrap();
+    let mut pixels = vec![0u8; (WIDTH * HEIGHT * 4) as usize];
+    texture.read_pixels(&mut pixels);
+
+    println!("first pixel: {:?}", &pixels[0..4]);
+}
+
+mod shader {
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+    }"#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    void main() {
+        gl_FragColor = vec4(1.0, 0.5, 0.0, 1.0);
+    }"#;
+}
@@ -100,8 +100,9 @@ impl SimpleEventHandler for Stage {
 
         let bindings = Bindings {
             vertex_buffers: vec![vertex_buffer],
-            index_buffer,
+            index_buffer: Some(index_buffer),
             images: vec![texture],
+            uniform_buffers: vec![],
         };
 
         let shader = Shader::new(
@@ -165,6 +166,7 @@ mod shader {
             uniforms: UniformBlockLayout {
                 uniforms: vec![UniformDesc::new("offset", UniformType::Float2)],
             },
+            uniform_blocks: vec![],
         }
     }
 
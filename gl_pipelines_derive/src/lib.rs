@@ -0,0 +1,350 @@
+//! Derive macros for `gl_pipelines`, enabled with its `derive` feature.
+//!
+//! `#[derive(Uniforms)]` implements `gl_pipelines::Uniforms` and `#[derive(VertexLayout)]`
+//! implements `gl_pipelines::VertexLayout`. Both read field types to pick a `UniformType` or
+//! a `VertexFormat` and check at compile time that fields are placed the way `gl_pipelines`
+//! reads them, i.e. one after another without padding.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Ident, Lit, Type};
+
+/// Implements `gl_pipelines::Uniforms` for a `#[repr(C)]` struct.
+///
+/// Fields map to uniforms of the same name: `f32`, `[f32; 2..=4]` and `(f32, ...)` tuples become
+/// `Float1..4`, `i32` and `u32` the same way become `Int1..4` and `UInt1..4`, `[[f32; R]; C]`
+/// becomes a matrix with `C` columns and `R` rows (`Mat4`, `Mat2x3` etc.) and an array of any of
+/// them becomes a uniform array. Other types, as well as `Bool` and `Sampler` uniforms, need
+/// an explicit `#[uniform(Bool)]` or `#[uniform(Sampler, array = 4)]`.
+#[proc_macro_derive(Uniforms, attributes(uniform))]
+pub fn derive_uniforms(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, Kind::Uniforms)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `gl_pipelines::VertexLayout` for a `#[repr(C)]` vertex struct.
+///
+/// Fields map to attributes of the same name: `f32`, `[f32; 2..=4]` and `(f32, ...)` tuples become
+/// `Float1..4`, arrays of `u8`, `u16` and `u32` become `Byte1..4`, `Short1..4` and `Int1..4`,
+/// `[[f32; 4]; 4]` becomes `Mat4`. Other types need an explicit `#[vertex(Float3)]`.
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, Kind::VertexLayout)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Uniforms,
+    VertexLayout,
+}
+
+impl Kind {
+    fn attribute(self) -> &'static str {
+        match self {
+            Kind::Uniforms => "uniform",
+            Kind::VertexLayout => "vertex",
+        }
+    }
+
+    /// Byte size of a single value of the `UniformType`/`VertexFormat` variant
+    fn size_of(self, variant: &str) -> Option<usize> {
+        let size = match (self, variant) {
+            (_, "Float1") | (_, "Int1") => 4,
+            (_, "Float2") | (_, "Int2") => 8,
+            (_, "Float3") | (_, "Int3") => 12,
+            (_, "Float4") | (_, "Int4") => 16,
+            (_, "Mat4") => 64,
+            (Kind::Uniforms, "UInt1") | (Kind::Uniforms, "Bool") | (Kind::Uniforms, "Sampler") => 4,
+            (Kind::Uniforms, "UInt2") => 8,
+            (Kind::Uniforms, "UInt3") => 12,
+            (Kind::Uniforms, "UInt4") => 16,
+            (Kind::Uniforms, "Mat2") => 16,
+            (Kind::Uniforms, "Mat3") => 36,
+            (Kind::Uniforms, "Mat2x3") | (Kind::Uniforms, "Mat3x2") => 24,
+            (Kind::Uniforms, "Mat2x4") | (Kind::Uniforms, "Mat4x2") => 32,
+            (Kind::Uniforms, "Mat3x4") | (Kind::Uniforms, "Mat4x3") => 48,
+            (Kind::VertexLayout, "Byte1") => 1,
+            (Kind::VertexLayout, "Byte2") => 2,
+            (Kind::VertexLayout, "Byte3") => 3,
+            (Kind::VertexLayout, "Byte4") => 4,
+            (Kind::VertexLayout, "Short1") => 2,
+            (Kind::VertexLayout, "Short2") => 4,
+            (Kind::VertexLayout, "Short3") => 6,
+            (Kind::VertexLayout, "Short4") => 8,
+            _ => return None,
+        };
+        Some(size)
+    }
+
+    /// Variant for a scalar type repeated `count` times
+    fn vector(self, scalar: &str, count: usize) -> Option<String> {
+        let prefix = match (self, scalar) {
+            (_, "f32") => "Float",
+            (Kind::Uniforms, "i32") => "Int",
+            (Kind::Uniforms, "u32") => "UInt",
+            (Kind::VertexLayout, "i32") | (Kind::VertexLayout, "u32") => "Int",
+            (Kind::VertexLayout, "u8") => "Byte",
+            (Kind::VertexLayout, "u16") => "Short",
+            _ => return None,
+        };
+        (1..=4).contains(&count).then(|| format!("{}{}", prefix, count))
+    }
+
+    /// Variant for a matrix of 32-bit floats
+    fn matrix(self, columns: usize, rows: usize) -> Option<String> {
+        match (self, columns, rows) {
+            (_, 4, 4) => Some("Mat4".to_string()),
+            (Kind::Uniforms, 2..=4, 2..=4) if columns == rows => Some(format!("Mat{}", columns)),
+            (Kind::Uniforms, 2..=4, 2..=4) => Some(format!("Mat{}x{}", columns, rows)),
+            _ => None,
+        }
+    }
+}
+
+/// Uniform or attribute generated for a field
+struct Field {
+    ident: Ident,
+    ty: Type,
+    variant: String,
+    array_count: usize,
+}
+
+impl Field {
+    fn size(&self, kind: Kind) -> usize {
+        kind.size_of(&self.variant).unwrap() * self.array_count
+    }
+}
+
+fn expand(input: DeriveInput, kind: Kind) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics, "generic structs are not supported"));
+    }
+    if !has_repr_c(&input) {
+        return Err(syn::Error::new_spanned(name, "struct should be #[repr(C)] to have a predictable layout"));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name, "only structs with named fields are supported")),
+        },
+        _ => return Err(syn::Error::new_spanned(name, "only structs are supported")),
+    };
+
+    let fields = fields
+        .iter()
+        .map(|field| parse_field(field, kind))
+        .collect::<syn::Result<Vec<Field>>>()?;
+
+    let checks = layout_checks(name, &fields, kind);
+
+    let body = match kind {
+        Kind::Uniforms => {
+            let uniforms = fields.iter().map(|field| {
+                let uniform_name = field.ident.to_string();
+                let variant = format_ident!("{}", field.variant);
+                let array_count = field.array_count;
+                quote! {
+                    ::gl_pipelines::UniformDesc::new(#uniform_name, ::gl_pipelines::UniformType::#variant)
+                        .array(#array_count)
+                }
+            });
+            quote! {
+                impl ::gl_pipelines::Uniforms for #name {
+                    fn uniform_layout() -> ::gl_pipelines::UniformBlockLayout {
+                        ::gl_pipelines::UniformBlockLayout {
+                            uniforms: ::std::vec![#(#uniforms),*],
+                        }
+                    }
+                }
+            }
+        }
+        Kind::VertexLayout => {
+            let attributes = fields.iter().map(|field| {
+                let attribute_name = field.ident.to_string();
+                let variant = format_ident!("{}", field.variant);
+                quote! {
+                    ::gl_pipelines::VertexAttribute::with_buffer(
+                        #attribute_name,
+                        ::gl_pipelines::VertexFormat::#variant,
+                        buffer_index
+                    )
+                }
+            });
+            quote! {
+                impl ::gl_pipelines::VertexLayout for #name {
+                    fn attributes(buffer_index: usize) -> ::std::vec::Vec<::gl_pipelines::VertexAttribute> {
+                        ::std::vec![#(#attributes),*]
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #body
+        #checks
+    })
+}
+
+fn has_repr_c(input: &DeriveInput) -> bool {
+    let mut repr_c = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+            }
+            Ok(())
+        });
+    }
+    repr_c
+}
+
+fn parse_field(field: &syn::Field, kind: Kind) -> syn::Result<Field> {
+    let ident = field.ident.clone().unwrap();
+    let mut variant = None;
+    let mut array_count = 1;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident(kind.attribute())) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("array") && kind == Kind::Uniforms {
+                let value: Expr = meta.value()?.parse()?;
+                array_count = array_len(&value)
+                    .ok_or_else(|| meta.error("expected an integer literal"))?;
+                return Ok(());
+            }
+            match meta.path.get_ident() {
+                Some(ident) if kind.size_of(&ident.to_string()).is_some() => {
+                    variant = Some(ident.to_string());
+                    Ok(())
+                }
+                _ => Err(meta.error(format!("unknown {} type", kind.attribute()))),
+            }
+        })?;
+    }
+
+    let (variant, array_count) = match variant {
+        Some(variant) => (variant, array_count),
+        None => map_type(&field.ty, kind).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                format!("can't pick a type for this field, specify it with #[{}(...)]", kind.attribute())
+            )
+        })?,
+    };
+
+    Ok(Field { ident, ty: field.ty.clone(), variant, array_count })
+}
+
+/// Picks the variant and array count for a field type
+fn map_type(ty: &Type, kind: Kind) -> Option<(String, usize)> {
+    if let Some(variant) = map_single(ty, kind) {
+        return Some((variant, 1));
+    }
+    match ty {
+        Type::Array(array) if kind == Kind::Uniforms => {
+            let variant = map_single(&array.elem, kind)?;
+            Some((variant, array_len(&array.len)?))
+        }
+        _ => None,
+    }
+}
+
+/// Picks the variant for a field type holding a single value
+fn map_single(ty: &Type, kind: Kind) -> Option<String> {
+    match ty {
+        Type::Path(_) => kind.vector(&scalar_name(ty)?, 1),
+        Type::Array(array) => {
+            let len = array_len(&array.len)?;
+            if let Type::Array(column) = &*array.elem {
+                if scalar_name(&column.elem).as_deref() != Some("f32") {
+                    return None;
+                }
+                return kind.matrix(len, array_len(&column.len)?);
+            }
+            kind.vector(&scalar_name(&array.elem)?, len)
+        }
+        Type::Tuple(tuple) => {
+            let first = scalar_name(tuple.elems.first()?)?;
+            if tuple.elems.iter().any(|elem| scalar_name(elem).as_ref() != Some(&first)) {
+                return None;
+            }
+            kind.vector(&first, tuple.elems.len())
+        }
+        Type::Paren(paren) => map_single(&paren.elem, kind),
+        Type::Group(group) => map_single(&group.elem, kind),
+        _ => None,
+    }
+}
+
+fn scalar_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().map(Ident::to_string),
+        _ => None,
+    }
+}
+
+fn array_len(len: &Expr) -> Option<usize> {
+    match len {
+        Expr::Lit(expr) => match &expr.lit {
+            Lit::Int(int) => int.base10_parse().ok(),
+            _ => None,
+        },
+        Expr::Group(group) => array_len(&group.expr),
+        _ => None,
+    }
+}
+
+/// Compile time assertions that every field starts right where the previous one ends,
+/// which is where `gl_pipelines` expects to find it
+fn layout_checks(name: &Ident, fields: &[Field], kind: Kind) -> TokenStream2 {
+    let mut offset = 0;
+    let mut checks = Vec::new();
+
+    for field in fields {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let size = field.size(kind);
+
+        let size_message = format!(
+            "field `{}` of `{}` should be {} bytes long to hold {}",
+            ident,
+            name,
+            size,
+            field.variant
+        );
+        let offset_message = format!(
+            "field `{}` of `{}` should be at offset {}, padding in between fields is not supported",
+            ident,
+            name,
+            offset
+        );
+        checks.push(quote! {
+            assert!(::core::mem::size_of::<#ty>() == #size, #size_message);
+            assert!(::core::mem::offset_of!(#name, #ident) == #offset, #offset_message);
+        });
+        offset += size;
+    }
+
+    // Vertex structs may have trailing padding, it goes into the stride of `VertexLayout::buffer_layout()`
+    if kind == Kind::Uniforms {
+        let size_message = format!("`{}` should be {} bytes long, trailing padding is not supported", name, offset);
+        checks.push(quote! {
+            assert!(::core::mem::size_of::<#name>() == #offset, #size_message);
+        });
+    }
+
+    quote! {
+        const _: () = {
+            #(#checks)*
+        };
+    }
+}
@@ -1,6 +1,6 @@
 use std::mem;
 use glow::HasContext;
-use crate::{Context, GlowContext};
+use crate::{Context, GlError, GlowContext};
 use crate::types_impl::{BufferType, IndexType, Usage};
 
 #[derive(Clone)]
@@ -14,90 +14,94 @@ pub struct Buffer {
 
 impl Buffer {
     pub fn immutable<T: bytemuck::Pod>(ctx: &mut Context, buffer_type: BufferType, data: &[T]) -> Buffer {
+        Self::try_immutable(ctx, buffer_type, data).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_immutable<T: bytemuck::Pod>(
+        ctx: &mut Context,
+        buffer_type: BufferType,
+        data: &[T]
+    ) -> Result<Buffer, GlError> {
         let index_type = if buffer_type == BufferType::IndexBuffer {
             Some(IndexType::for_type::<T>())
         } else {
             None
         };
 
-        let gl_target = gl_buffer_target(&buffer_type);
-        let gl_usage = gl_usage(&Usage::Immutable);
-        let size = mem::size_of_val(data);
-
-        let gl = &ctx.glow_ctx.0.gl;
-
-        let gl_buf = unsafe {
-            let gl_buf = gl.create_buffer().unwrap();
-            ctx.cache.store_buffer_binding(gl_target);
-            ctx.cache.bind_buffer(gl_target, Some(gl_buf), index_type);
-
-            let data_casted: &[u8] = bytemuck::cast_slice(&data[..]);
-            gl.buffer_data_u8_slice(gl_target, data_casted, gl_usage);
-            ctx.cache.restore_buffer_binding(gl_target);
-            gl_buf
-        };
-
-        Buffer {
-            glow_ctx: ctx.glow_ctx.clone(),
-            gl_buf,
-            buffer_type,
-            size,
-            index_type,
-        }
+        let data_casted: &[u8] = bytemuck::cast_slice(data);
+        Self::allocate(ctx, buffer_type, index_type, Usage::Immutable, BufferStorage::Data(data_casted))
     }
 
     pub fn stream(ctx: &mut Context, buffer_type: BufferType, size: usize) -> Buffer {
+        Self::try_stream(ctx, buffer_type, size).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_stream(ctx: &mut Context, buffer_type: BufferType, size: usize) -> Result<Buffer, GlError> {
         let index_type = if buffer_type == BufferType::IndexBuffer {
             Some(IndexType::Short)
         } else {
             None
         };
 
-        let gl_target = gl_buffer_target(&buffer_type);
-        let gl_usage = gl_usage(&Usage::Stream);
-
-        let gl = &ctx.glow_ctx.0.gl;
+        Self::allocate(ctx, buffer_type, index_type, Usage::Stream, BufferStorage::Size(size))
+    }
 
-        let gl_buf = unsafe {
-            let gl_buf = gl.create_buffer().unwrap();
-            ctx.cache.store_buffer_binding(gl_target);
-            ctx.cache.bind_buffer(gl_target, Some(gl_buf), None);
-            gl.buffer_data_size(gl_target, size as _, gl_usage);
-            ctx.cache.restore_buffer_binding(gl_target);
-            gl_buf
-        };
+    pub fn index_stream(ctx: &mut Context, index_type: IndexType, size: usize) -> Buffer {
+        Self::try_index_stream(ctx, index_type, size).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        Buffer {
-            glow_ctx: ctx.glow_ctx.clone(),
-            gl_buf,
-            buffer_type,
-            size,
-            index_type,
-        }
+    pub fn try_index_stream(ctx: &mut Context, index_type: IndexType, size: usize) -> Result<Buffer, GlError> {
+        Self::allocate(ctx, BufferType::IndexBuffer, Some(index_type), Usage::Stream, BufferStorage::Size(size))
     }
 
-    pub fn index_stream(ctx: &mut Context, index_type: IndexType, size: usize) -> Buffer {
-        let gl_target = gl_buffer_target(&BufferType::IndexBuffer);
-        let gl_usage = gl_usage(&Usage::Stream);
+    fn allocate(
+        ctx: &mut Context,
+        buffer_type: BufferType,
+        index_type: Option<IndexType>,
+        usage: Usage,
+        storage: BufferStorage,
+    ) -> Result<Buffer, GlError> {
+        let gl_target = gl_buffer_target(&buffer_type);
+        let gl_usage = gl_usage(&usage);
+        let size = match storage {
+            BufferStorage::Data(data) => data.len(),
+            BufferStorage::Size(size) => size,
+        };
 
         let gl = &ctx.glow_ctx.0.gl;
 
-        let gl_buf = unsafe {
-            let gl_buf = gl.create_buffer().unwrap();
+        let (gl_buf, result) = unsafe {
+            let gl_buf = gl.create_buffer().map_err(GlError::ObjectCreation)?;
             ctx.cache.store_buffer_binding(gl_target);
-            ctx.cache.bind_buffer(gl_target, Some(gl_buf), None);
-            gl.buffer_data_size(gl_target, size as _, gl_usage);
+            ctx.cache.bind_buffer(gl_target, Some(gl_buf), index_type);
+
+            // Errors of earlier calls are reported before being dropped
+            ctx.poll_gl_errors("calls made before Buffer allocation");
+            GlError::clear(gl);
+            match storage {
+                BufferStorage::Data(data) => gl.buffer_data_u8_slice(gl_target, data, gl_usage),
+                BufferStorage::Size(size) => gl.buffer_data_size(gl_target, size as _, gl_usage),
+            }
+            let result = GlError::check(gl);
+
             ctx.cache.restore_buffer_binding(gl_target);
-            gl_buf
+            (gl_buf, result)
         };
 
-        Buffer {
+        if let Err(e) = result {
+            unsafe {
+                gl.delete_buffer(gl_buf);
+            }
+            return Err(e);
+        }
+
+        Ok(Buffer {
             glow_ctx: ctx.glow_ctx.clone(),
             gl_buf,
-            buffer_type: BufferType::IndexBuffer,
+            buffer_type,
             size,
-            index_type: Some(index_type),
-        }
+            index_type,
+        })
     }
 
     pub fn update<T: bytemuck::Pod>(&self, ctx: &mut Context, data: &[T]) {
@@ -118,12 +122,19 @@ impl Buffer {
             ctx.glow_ctx.0.gl.buffer_sub_data_u8_slice(gl_target, 0, data_casted);
         };
         ctx.cache.restore_buffer_binding(gl_target);
+
+        ctx.poll_gl_errors("Buffer::update");
     }
 
     pub fn size(&self) -> usize {
         self.size
     }
 
+    /// Names the buffer in GPU captures and debug messages, `None` removes the name.
+    pub fn set_label(&self, label: Option<&str>) {
+        self.glow_ctx.object_label(glow::BUFFER, self.gl_buf, label);
+    }
+
     pub fn delete(&self) {
         unsafe {
             self.glow_ctx.0.gl.delete_buffer(self.gl_buf);
@@ -131,10 +142,16 @@ impl Buffer {
     }
 }
 
+enum BufferStorage<'a> {
+    Data(&'a [u8]),
+    Size(usize),
+}
+
 fn gl_buffer_target(buffer_type: &BufferType) -> u32 {
     match buffer_type {
         BufferType::VertexBuffer => glow::ARRAY_BUFFER,
         BufferType::IndexBuffer => glow::ELEMENT_ARRAY_BUFFER,
+        BufferType::UniformBuffer => glow::UNIFORM_BUFFER,
     }
 }
 
@@ -1,17 +1,22 @@
 use glow::HasContext;
-use crate::{CachedAttribute, ColorMask, MAX_SHADERSTAGE_IMAGES, MAX_VERTEX_ATTRIBUTES, Pipeline};
+use crate::{CachedAttribute, ColorMask, MAX_SHADERSTAGE_IMAGES, MAX_SHADERSTAGE_UNIFORM_BLOCKS, MAX_VERTEX_ATTRIBUTES, Pipeline};
 use crate::glow_context::GlowContext;
-use crate::types_impl::{BlendState, CullFace, IndexType, StencilState};
+use crate::types_impl::{BlendState, Comparison, CullFace, IndexType, StencilState};
 
 pub(crate) struct GlCache {
     pub(crate) glow_ctx: GlowContext,
     pub(crate) stored_index_buffer: Option<glow::Buffer>,
     pub(crate) stored_index_type: Option<IndexType>,
     pub(crate) stored_vertex_buffer: Option<glow::Buffer>,
+    pub(crate) stored_uniform_buffer: Option<glow::Buffer>,
     pub(crate) stored_texture: Option<glow::Texture>,
     pub(crate) index_buffer: Option<glow::Buffer>,
     pub(crate) index_type: Option<IndexType>,
     pub(crate) vertex_buffer: Option<glow::Buffer>,
+    /// Generic `GL_UNIFORM_BUFFER` binding, used for uploads
+    pub(crate) uniform_buffer: Option<glow::Buffer>,
+    /// Indexed `GL_UNIFORM_BUFFER` bindings uniform blocks read from
+    pub(crate) uniform_buffers: [Option<glow::Buffer>; MAX_SHADERSTAGE_UNIFORM_BLOCKS],
     pub(crate) textures: [Option<glow::Texture>; MAX_SHADERSTAGE_IMAGES],
     pub(crate) cur_pipeline: Option<Pipeline>,
     pub(crate) color_blend: Option<BlendState>,
@@ -19,6 +24,12 @@ pub(crate) struct GlCache {
     pub(crate) stencil: Option<StencilState>,
     pub(crate) color_write: ColorMask,
     pub(crate) cull_face: CullFace,
+    pub(crate) primitive_restart: Option<u32>,
+    pub(crate) depth_write_offset: Option<(f32, f32)>,
+    pub(crate) depth_test: Option<Comparison>,
+    pub(crate) depth_write: bool,
+    pub(crate) depth_range: (f32, f32),
+    pub(crate) depth_clamp: bool,
     pub(crate) attributes: [Option<CachedAttribute>; MAX_VERTEX_ATTRIBUTES],
 }
 
@@ -33,6 +44,13 @@ impl GlCache {
                     gl.bind_buffer(target, buffer);
                 }
             }
+        } else if target == glow::UNIFORM_BUFFER {
+            if self.uniform_buffer != buffer {
+                self.uniform_buffer = buffer;
+                unsafe {
+                    gl.bind_buffer(target, buffer);
+                }
+            }
         } else {
             if self.index_buffer != buffer {
                 self.index_buffer = buffer;
@@ -47,6 +65,8 @@ impl GlCache {
     pub(crate) fn store_buffer_binding(&mut self, target: u32) {
         if target == glow::ARRAY_BUFFER {
             self.stored_vertex_buffer = self.vertex_buffer;
+        } else if target == glow::UNIFORM_BUFFER {
+            self.stored_uniform_buffer = self.uniform_buffer;
         } else {
             self.stored_index_buffer = self.index_buffer;
             self.stored_index_type = self.index_type;
@@ -62,6 +82,10 @@ impl GlCache {
                 },
                 _ => ()
             }
+        } else if target == glow::UNIFORM_BUFFER {
+            if let Some(ub) = self.stored_uniform_buffer.take() {
+                self.bind_buffer(target, Some(ub), None);
+            }
         } else {
             match self.stored_index_buffer {
                 Some(ib) => {
@@ -73,6 +97,18 @@ impl GlCache {
         }
     }
 
+    /// Binds `buffer` to the uniform buffer binding point `index`, which also replaces
+    /// the generic binding
+    pub(crate) fn bind_uniform_buffer(&mut self, index: usize, buffer: Option<glow::Buffer>) {
+        if self.uniform_buffers[index] != buffer {
+            unsafe {
+                self.glow_ctx.0.gl.bind_buffer_base(glow::UNIFORM_BUFFER, index as u32, buffer);
+            }
+            self.uniform_buffers[index] = buffer;
+            self.uniform_buffer = buffer;
+        }
+    }
+
     pub(crate) fn bind_texture(&mut self, slot_index: usize, texture: Option<glow::Texture>) {
         let gl = &self.glow_ctx.0.gl;
         unsafe {
@@ -98,6 +134,11 @@ impl GlCache {
 
         self.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None, None);
         self.index_buffer = None;
+
+        for ix in 0..MAX_SHADERSTAGE_UNIFORM_BLOCKS {
+            self.bind_uniform_buffer(ix, None);
+        }
+        self.bind_buffer(glow::UNIFORM_BUFFER, None, None);
     }
 
     pub(crate) fn clear_texture_bindings(&mut self) {
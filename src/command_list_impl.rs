@@ -0,0 +1,313 @@
+use crate::{Bindings, Context, IndexType, PassAction, Pipeline, RenderPass};
+
+/// GL names of [`Bindings`], which can be moved to other threads and recorded into a [`CommandList`].
+///
+/// Doesn't keep anything alive, so the `Bindings` it was made from should outlive submission
+/// of every list it was recorded into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawBindings {
+    pub(crate) vertex_buffers: Vec<glow::Buffer>,
+    pub(crate) vertex_buffer_sizes: Vec<usize>,
+    pub(crate) index_buffer: Option<glow::Buffer>,
+    pub(crate) index_buffer_size: usize,
+    pub(crate) index_type: Option<IndexType>,
+    pub(crate) images: Vec<Option<glow::Texture>>,
+    pub(crate) uniform_buffers: Vec<glow::Buffer>,
+    pub(crate) uniform_buffer_sizes: Vec<usize>,
+}
+
+impl From<&Bindings> for RawBindings {
+    fn from(bindings: &Bindings) -> Self {
+        RawBindings {
+            vertex_buffers: bindings.vertex_buffers.iter().map(|vb| vb.gl_buf).collect(),
+            vertex_buffer_sizes: bindings.vertex_buffers.iter().map(|vb| vb.size).collect(),
+            index_buffer: bindings.index_buffer.as_ref().map(|ib| ib.gl_buf),
+            index_buffer_size: bindings.index_buffer.as_ref().map_or(0, |ib| ib.size),
+            index_type: bindings.index_buffer.as_ref().and_then(|ib| ib.index_type),
+            images: bindings.images.iter().map(|image| image.texture).collect(),
+            uniform_buffers: bindings.uniform_buffers.iter().map(|ub| ub.gl_buf).collect(),
+            uniform_buffer_sizes: bindings.uniform_buffers.iter().map(|ub| ub.size).collect(),
+        }
+    }
+}
+
+/// What applying bindings needs to know, shared by [`Bindings`] and [`RawBindings`],
+/// so bindings applied directly are never converted.
+pub(crate) trait BindingsSource {
+    fn vertex_buffer_count(&self) -> usize;
+    /// GL name and size in bytes
+    fn vertex_buffer(&self, index: usize) -> (glow::Buffer, usize);
+    /// GL name, size in bytes and type of indices
+    fn index_buffer(&self) -> Option<(glow::Buffer, usize, Option<IndexType>)>;
+    fn image_count(&self) -> usize;
+    fn image(&self, index: usize) -> Option<glow::Texture>;
+    fn uniform_buffer_count(&self) -> usize;
+    /// GL name and size in bytes
+    fn uniform_buffer(&self, index: usize) -> (glow::Buffer, usize);
+}
+
+impl BindingsSource for Bindings {
+    fn vertex_buffer_count(&self) -> usize {
+        self.vertex_buffers.len()
+    }
+
+    fn vertex_buffer(&self, index: usize) -> (glow::Buffer, usize) {
+        let buffer = &self.vertex_buffers[index];
+        (buffer.gl_buf, buffer.size)
+    }
+
+    fn index_buffer(&self) -> Option<(glow::Buffer, usize, Option<IndexType>)> {
+        self.index_buffer.as_ref().map(|ib| (ib.gl_buf, ib.size, ib.index_type))
+    }
+
+    fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    fn image(&self, index: usize) -> Option<glow::Texture> {
+        self.images[index].texture
+    }
+
+    fn uniform_buffer_count(&self) -> usize {
+        self.uniform_buffers.len()
+    }
+
+    fn uniform_buffer(&self, index: usize) -> (glow::Buffer, usize) {
+        let buffer = &self.uniform_buffers[index];
+        (buffer.gl_buf, buffer.size)
+    }
+}
+
+impl BindingsSource for RawBindings {
+    fn vertex_buffer_count(&self) -> usize {
+        self.vertex_buffers.len()
+    }
+
+    fn vertex_buffer(&self, index: usize) -> (glow::Buffer, usize) {
+        (self.vertex_buffers[index], self.vertex_buffer_sizes[index])
+    }
+
+    fn index_buffer(&self) -> Option<(glow::Buffer, usize, Option<IndexType>)> {
+        self.index_buffer.map(|ib| (ib, self.index_buffer_size, self.index_type))
+    }
+
+    fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    fn image(&self, index: usize) -> Option<glow::Texture> {
+        self.images[index]
+    }
+
+    fn uniform_buffer_count(&self) -> usize {
+        self.uniform_buffers.len()
+    }
+
+    fn uniform_buffer(&self, index: usize) -> (glow::Buffer, usize) {
+        (self.uniform_buffers[index], self.uniform_buffer_sizes[index])
+    }
+}
+
+/// Single recorded call, named after the [`Context`] method it replays.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    BeginPass { pass: Option<RenderPass>, action: PassAction },
+    EndRenderPass,
+    ApplyPipeline(Pipeline),
+    ApplyBindings(RawBindings),
+    /// Uniforms struct copied as 32 bit words
+    ApplyUniforms(Vec<u32>),
+    ApplyViewport { x: i32, y: i32, w: i32, h: i32 },
+    ApplyScissorRect { x: i32, y: i32, w: i32, h: i32 },
+    Clear { color: Option<(f32, f32, f32, f32)>, depth: Option<f32>, stencil: Option<i32> },
+    Draw { base_element: i32, num_elements: i32, num_instances: i32 },
+    DrawArrays { first_vertex: i32, num_vertices: i32, num_instances: i32 },
+    PushDebugGroup(String),
+    PopDebugGroup,
+}
+
+/// Calls recorded for a later [`Context::submit()`].
+///
+/// Recording makes no GL calls, so lists can be built on worker threads and inspected
+/// without any context. Handles are checked when the list gets submitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandList {
+    commands: Vec<Command>,
+}
+
+impl CommandList {
+    pub fn new() -> CommandList {
+        CommandList::default()
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Removes all commands, keeping allocated memory for the next frame
+    pub fn reset(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn push(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    pub fn begin_default_pass(&mut self, action: PassAction) {
+        self.begin_pass(None, action);
+    }
+
+    pub fn begin_pass(&mut self, pass: impl Into<Option<RenderPass>>, action: PassAction) {
+        self.push(Command::BeginPass { pass: pass.into(), action });
+    }
+
+    pub fn end_render_pass(&mut self) {
+        self.push(Command::EndRenderPass);
+    }
+
+    pub fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        self.push(Command::ApplyPipeline(*pipeline));
+    }
+
+    pub fn apply_bindings(&mut self, bindings: &RawBindings) {
+        self.push(Command::ApplyBindings(bindings.clone()));
+    }
+
+    /// `Pod` rules out padding, which would be copied uninitialized otherwise
+    pub fn apply_uniforms<U: bytemuck::Pod>(&mut self, uniforms: &U) {
+        let bytes = bytemuck::bytes_of(uniforms);
+        let mut words = vec![0u32; bytes.len().div_ceil(4)];
+        bytemuck::cast_slice_mut::<u32, u8>(&mut words)[..bytes.len()].copy_from_slice(bytes);
+        self.push(Command::ApplyUniforms(words));
+    }
+
+    pub fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.push(Command::ApplyViewport { x, y, w, h });
+    }
+
+    pub fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.push(Command::ApplyScissorRect { x, y, w, h });
+    }
+
+    pub fn clear(
+        &mut self,
+        color: Option<(f32, f32, f32, f32)>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        self.push(Command::Clear { color, depth, stencil });
+    }
+
+    pub fn draw(&mut self, base_element: i32, num_elements: i32, num_instances: i32) {
+        self.push(Command::Draw { base_element, num_elements, num_instances });
+    }
+
+    pub fn draw_arrays(&mut self, first_vertex: i32, num_vertices: i32, num_instances: i32) {
+        self.push(Command::DrawArrays { first_vertex, num_vertices, num_instances });
+    }
+
+    pub fn push_debug_group(&mut self, name: &str) {
+        self.push(Command::PushDebugGroup(name.to_owned()));
+    }
+
+    pub fn pop_debug_group(&mut self) {
+        self.push(Command::PopDebugGroup);
+    }
+}
+
+impl Context {
+    /// Replays `list` in order, same as calling the corresponding `Context` methods directly.
+    pub fn submit(&mut self, list: &CommandList) {
+        for command in list.commands() {
+            match command {
+                Command::BeginPass { pass, action } => self.begin_pass(*pass, action.clone()),
+                Command::EndRenderPass => self.end_render_pass(),
+                Command::ApplyPipeline(pipeline) => self.apply_pipeline(pipeline),
+                Command::ApplyBindings(bindings) => self.apply_raw_bindings(bindings),
+                Command::ApplyUniforms(words) => {
+                    self.apply_uniforms_from_bytes(words.as_ptr() as *const u8, words.len() * 4)
+                }
+                Command::ApplyViewport { x, y, w, h } => self.apply_viewport(*x, *y, *w, *h),
+                Command::ApplyScissorRect { x, y, w, h } => self.apply_scissor_rect(*x, *y, *w, *h),
+                Command::Clear { color, depth, stencil } => self.clear(*color, *depth, *stencil),
+                Command::Draw { base_element, num_elements, num_instances } => {
+                    self.draw(*base_element, *num_elements, *num_instances)
+                }
+                Command::DrawArrays { first_vertex, num_vertices, num_instances } => {
+                    self.draw_arrays(*first_vertex, *num_vertices, *num_instances)
+                }
+                Command::PushDebugGroup(name) => self.push_debug_group(name),
+                Command::PopDebugGroup => self.pop_debug_group(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slot_map::SlotMap;
+    use super::*;
+
+    #[test]
+    fn records_without_context() {
+        let pipeline = Pipeline(SlotMap::new().insert(()));
+        let bindings = RawBindings {
+            vertex_buffers: vec![],
+            vertex_buffer_sizes: vec![],
+            index_buffer: None,
+            index_buffer_size: 0,
+            index_type: Some(IndexType::Short),
+            images: vec![None],
+            uniform_buffers: vec![],
+            uniform_buffer_sizes: vec![],
+        };
+        let clear = PassAction::clear_color(0.0, 0.0, 0.0, 1.0);
+
+        let mut list = CommandList::new();
+        list.begin_default_pass(clear.clone());
+        list.push_debug_group("sprites");
+        list.apply_pipeline(&pipeline);
+        list.apply_bindings(&bindings);
+        list.apply_uniforms(&[1.5f32, -2.0]);
+        // Copied into whole words, the tail padded with zeroes
+        list.apply_uniforms(&[1u8, 2, 3]);
+        list.apply_viewport(0, 0, 640, 480);
+        list.apply_scissor_rect(10, 20, 30, 40);
+        list.draw(6, 12, 2);
+        list.draw_arrays(0, 3, 1);
+        list.pop_debug_group();
+        list.clear(None, Some(1.0), None);
+        list.end_render_pass();
+
+        assert_eq!(
+            list.commands(),
+            [
+                Command::BeginPass { pass: None, action: clear },
+                Command::PushDebugGroup("sprites".to_string()),
+                Command::ApplyPipeline(pipeline),
+                Command::ApplyBindings(bindings),
+                Command::ApplyUniforms(vec![1.5f32.to_bits(), (-2.0f32).to_bits()]),
+                Command::ApplyUniforms(vec![u32::from_ne_bytes([1, 2, 3, 0])]),
+                Command::ApplyViewport { x: 0, y: 0, w: 640, h: 480 },
+                Command::ApplyScissorRect { x: 10, y: 20, w: 30, h: 40 },
+                Command::Draw { base_element: 6, num_elements: 12, num_instances: 2 },
+                Command::DrawArrays { first_vertex: 0, num_vertices: 3, num_instances: 1 },
+                Command::PopDebugGroup,
+                Command::Clear { color: None, depth: Some(1.0), stencil: None },
+                Command::EndRenderPass,
+            ],
+        );
+        assert_eq!(list.len(), 13);
+
+        list.reset();
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::RefCell;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use glow::HasContext;
+use crate::Context;
+
+/// Severity of a [`DebugMessage`], ordered from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: u32) -> DebugSeverity {
+        match severity {
+            glow::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            glow::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            glow::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DebugMessage<'a> {
+    /// `GL_DEBUG_SOURCE_*` value
+    pub source: u32,
+    /// `GL_DEBUG_TYPE_*` value
+    pub message_type: u32,
+    pub id: u32,
+    pub severity: DebugSeverity,
+    pub message: &'a str,
+}
+
+/// Where debug messages go.
+pub enum DebugOutput {
+    /// Messages are logged with the `log` crate, `High` severity as errors, `Medium` as warnings,
+    /// `Low` as info and notifications as debug
+    #[cfg(feature = "log")]
+    Log,
+    /// Messages are passed to the callback, which should not panic
+    Callback(Box<dyn FnMut(&DebugMessage)>),
+}
+
+/// How debug messages are obtained, see [`Context::enable_debug_output()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugMode {
+    /// Driver reports messages on its own through `KHR_debug`/`ARB_debug_output`
+    Callback,
+    /// `glGetError` is polled after each `Context` call, so only errors are reported
+    ErrorPolling,
+}
+
+pub(crate) struct DebugState {
+    output: DebugOutput,
+    min_severity: DebugSeverity,
+    mode: DebugMode,
+}
+
+impl DebugState {
+    fn report(&mut self, message: &DebugMessage) {
+        if message.severity < self.min_severity {
+            return;
+        }
+
+        match &mut self.output {
+            #[cfg(feature = "log")]
+            DebugOutput::Log => {
+                let level = match message.severity {
+                    DebugSeverity::High => log::Level::Error,
+                    DebugSeverity::Medium => log::Level::Warn,
+                    DebugSeverity::Low => log::Level::Info,
+                    DebugSeverity::Notification => log::Level::Debug,
+                };
+                log::log!(
+                    level,
+                    "GL debug message {:#x} (source {:#x}, type {:#x}): {}",
+                    message.id,
+                    message.source,
+                    message.message_type,
+                    message.message
+                );
+            }
+            DebugOutput::Callback(callback) => callback(message),
+        }
+    }
+}
+
+extern "system" fn debug_callback(
+    source: u32,
+    message_type: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    user_param: *mut c_void
+) {
+    let message = unsafe {
+        if length >= 0 {
+            let bytes = std::slice::from_raw_parts(message as *const u8, length as usize);
+            String::from_utf8_lossy(bytes)
+        } else {
+            CStr::from_ptr(message).to_string_lossy()
+        }
+    };
+
+    let state = unsafe { &*(user_param as *const RefCell<DebugState>) };
+    // Messages caused by GL calls made from the callback itself are dropped
+    if let Ok(mut state) = state.try_borrow_mut() {
+        state.report(&DebugMessage {
+            source,
+            message_type,
+            id,
+            severity: DebugSeverity::from_gl(severity),
+            message: message.trim_end(),
+        });
+    }
+}
+
+fn gl_error_name(error: u32) -> &'static str {
+    match error {
+        glow::INVALID_ENUM => "GL_INVALID_ENUM",
+        glow::INVALID_VALUE => "GL_INVALID_VALUE",
+        glow::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        glow::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        glow::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        glow::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        glow::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        _ => "unknown GL error",
+    }
+}
+
+impl Context {
+    /// Starts reporting driver messages with at least `min_severity` to `output`.
+    ///
+    /// Uses `KHR_debug` or `ARB_debug_output` when available (a debug context, see
+    /// `window::Conf::debug_context`, makes drivers much more talkative), otherwise falls back
+    /// to polling `glGetError` after each call made through the `Context`, which costs a sync
+    /// with the driver every time. Contexts created with [`Context::from_glow()`] always poll.
+    pub fn enable_debug_output(&mut self, output: DebugOutput, min_severity: DebugSeverity) -> DebugMode {
+        self.disable_debug_output();
+
+        let gl = &self.glow_ctx.0.gl;
+        let debug_message_callback = self.glow_ctx.0.raw.debug_message_callback;
+        let has_debug_output = self.glow_ctx.0.khr_debug
+            || gl.supported_extensions().contains("GL_ARB_debug_output");
+
+        let mode = match debug_message_callback {
+            Some(_) if has_debug_output => DebugMode::Callback,
+            _ => DebugMode::ErrorPolling,
+        };
+
+        let state = Box::new(RefCell::new(DebugState { output, min_severity, mode }));
+
+        unsafe {
+            match (mode, debug_message_callback) {
+                (DebugMode::Callback, Some(debug_message_callback)) => {
+                    gl.enable(glow::DEBUG_OUTPUT);
+                    // Makes the callback run inside the offending call, on this thread
+                    gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+                    debug_message_callback(
+                        Some(debug_callback),
+                        &*state as *const RefCell<DebugState> as *const c_void
+                    );
+                }
+                _ => {
+                    // Errors made before are not ours to report
+                    while gl.get_error() != glow::NO_ERROR {}
+                }
+            }
+        }
+
+        self.debug = Some(state);
+        mode
+    }
+
+    pub fn disable_debug_output(&mut self) {
+        let state = match self.debug.take() {
+            Some(state) => state,
+            None => return,
+        };
+
+        if state.borrow().mode == DebugMode::Callback {
+            let gl = &self.glow_ctx.0.gl;
+            unsafe {
+                if let Some(debug_message_callback) = self.glow_ctx.0.raw.debug_message_callback {
+                    debug_message_callback(None, std::ptr::null());
+                }
+                gl.disable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+                gl.disable(glow::DEBUG_OUTPUT);
+            }
+        }
+    }
+
+    /// Opens a named group of GL calls, shown as a tree node in RenderDoc, apitrace and such.
+    ///
+    /// Every pass gets its own group, named after the pass label (see [`crate::RenderPass::set_label()`]).
+    /// Does nothing without `KHR_debug`.
+    pub fn push_debug_group(&mut self, name: &str) {
+        if self.glow_ctx.0.khr_debug {
+            unsafe {
+                self.glow_ctx.0.gl.push_debug_group(glow::DEBUG_SOURCE_APPLICATION, 0, name);
+            }
+        }
+    }
+
+    /// Closes the group opened by the last [`Context::push_debug_group()`].
+    pub fn pop_debug_group(&mut self) {
+        if self.glow_ctx.0.khr_debug {
+            unsafe {
+                self.glow_ctx.0.gl.pop_debug_group();
+            }
+        }
+    }
+
+    /// Reports errors of the calls made by `operation` in the error polling debug mode.
+    pub(crate) fn poll_gl_errors(&self, operation: &str) {
+        let state = match &self.debug {
+            Some(state) => state,
+            None => return,
+        };
+        let mut state = state.borrow_mut();
+        if state.mode != DebugMode::ErrorPolling {
+            return;
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+        loop {
+            let error = unsafe { gl.get_error() };
+            if error == glow::NO_ERROR {
+                break;
+            }
+
+            let message = format!("{} ({:#x}) in {}", gl_error_name(error), error, operation);
+            state.report(&DebugMessage {
+                source: glow::DEBUG_SOURCE_API,
+                message_type: glow::DEBUG_TYPE_ERROR,
+                id: error,
+                severity: DebugSeverity::High,
+                message: &message,
+            });
+        }
+    }
+}
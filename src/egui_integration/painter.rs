@@ -1,5 +1,5 @@
 use egui::epaint::Vertex;
-use crate::{Bindings, BlendFactor, BlendState, BlendValue, Buffer, BufferLayout, BufferType, Context, CullFace, Equation, FilterMode, PassAction, Pipeline, PipelineParams, Shader, Texture, TextureFormat, TextureKind, TextureParams, TextureWrap, VertexAttribute, VertexFormat};
+use crate::{Bindings, BlendFactor, BlendState, BlendValue, Buffer, BufferLayout, BufferType, Context, CullFace, Equation, FilterMode, PassAction, Pipeline, PipelineParams, Shader, ShaderPreprocessor, ShaderType, Texture, TextureFormat, TextureKind, TextureParams, TextureWrap, VertexAttribute, VertexFormat};
 
 pub struct CallbackFn {
     #[allow(clippy::type_complexity)]
@@ -14,7 +14,10 @@ pub struct Painter {
 
 impl Painter {
     pub fn new(ctx: &mut Context) -> Painter {
-        let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta());
+        let preprocessor = ShaderPreprocessor::for_context(ctx);
+        let vertex = preprocessor.process(ShaderType::Vertex, shader::VERTEX).unwrap();
+        let fragment = preprocessor.process(ShaderType::Fragment, shader::FRAGMENT).unwrap();
+        let shader = Shader::new(ctx, &vertex, &fragment, shader::meta());
 
         let pipeline = Pipeline::with_params(
             ctx,
@@ -49,8 +52,9 @@ impl Painter {
 
         let bindings = Bindings {
             vertex_buffers: vec![vertex_buffer],
-            index_buffer,
+            index_buffer: Some(index_buffer),
             images: vec![crate::Texture::empty(ctx)],
+            uniform_buffers: vec![],
         };
 
         Painter {
@@ -255,12 +259,12 @@ impl Painter {
             self.bindings.vertex_buffers[0].update(ctx, &mesh.vertices);
 
             let indices_size_bytes = mesh.indices.len() * std::mem::size_of::<u16>();
-            if self.bindings.index_buffer.size() < indices_size_bytes {
-                self.bindings.index_buffer.delete();
-                self.bindings.index_buffer =
-                    Buffer::stream(ctx, BufferType::IndexBuffer, indices_size_bytes);
+            let index_buffer = self.bindings.index_buffer.as_mut().unwrap();
+            if index_buffer.size() < indices_size_bytes {
+                index_buffer.delete();
+                *index_buffer = Buffer::stream(ctx, BufferType::IndexBuffer, indices_size_bytes);
             }
-            self.bindings.index_buffer.update(ctx, &mesh.indices);
+            index_buffer.update(ctx, &mesh.indices);
 
             self.bindings.images[0] = match mesh.texture_id {
                 egui::TextureId::Managed(id) => {
@@ -412,6 +416,7 @@ mod shader {
             uniforms: UniformBlockLayout {
                 uniforms: vec![UniformDesc::new("u_screen_size", UniformType::Float2)],
             },
+            uniform_blocks: vec![],
         }
     }
 
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt::Display;
+use glow::HasContext;
+use crate::FramebufferError;
+
+/// Failure of a GL object creation, returned by `try_` constructors.
+#[derive(Clone, Debug)]
+pub enum GlError {
+    /// GL refused to create an object name
+    ObjectCreation(String),
+    /// `GL_OUT_OF_MEMORY` reported after storage allocation
+    OutOfMemory,
+    /// Any other error reported by `glGetError` after storage allocation,
+    /// e.g. `GL_INVALID_VALUE` for a texture bigger than `GL_MAX_TEXTURE_SIZE`
+    Other(u32),
+    /// Attachments of a render pass don't form a complete framebuffer
+    Framebuffer(FramebufferError),
+    /// Pixel data length doesn't match the size of the texture or of the updated region, in bytes
+    InvalidDataSize { expected: usize, actual: usize },
+    /// Updated region doesn't fit into the texture
+    RegionOutOfBounds { x_offset: i32, y_offset: i32, width: i32, height: i32 },
+}
+
+impl GlError {
+    /// Drops errors left by previous calls, so the check after allocation reports its own result only
+    pub(crate) unsafe fn clear(gl: &glow::Context) {
+        while gl.get_error() != glow::NO_ERROR {}
+    }
+
+    pub(crate) unsafe fn check(gl: &glow::Context) -> Result<(), GlError> {
+        match gl.get_error() {
+            glow::NO_ERROR => Ok(()),
+            glow::OUT_OF_MEMORY => Err(GlError::OutOfMemory),
+            error => Err(GlError::Other(error)),
+        }
+    }
+}
+
+impl From<FramebufferError> for GlError {
+    fn from(e: FramebufferError) -> GlError {
+        GlError::Framebuffer(e)
+    }
+}
+
+impl Display for GlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for GlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GlError::Framebuffer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Mismatch between vertex attributes and buffer layouts of a pipeline.
+#[derive(Clone, Debug)]
+pub enum PipelineError {
+    /// Attribute refers to a buffer layout which is not in the list
+    MissingBufferLayout { attribute: String, buffer_index: usize },
+    /// Vertex stride is over 255 bytes, which is not supported by WebGL 1
+    StrideTooLarge { buffer_index: usize, stride: i32 },
+    /// Shader placed the attribute outside of the attribute array allocated for the pipeline
+    AttributeLocationOutOfRange { attribute: String, location: u32 },
+    /// Restart index the context can't use: without `glPrimitiveRestartIndex` only the fixed
+    /// ones (`0xFF`, `0xFFFF` and `0xFFFFFFFF`) are supported, and only by GL 4.3 and GLES 3
+    UnsupportedPrimitiveRestart { restart_index: u32 },
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Mismatch between a Rust struct and the std140 layout of a uniform block,
+/// see [`crate::UniformBlockLayout::check_std140()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Std140Error {
+    /// Struct has a different number of fields than the block has uniforms
+    FieldCount { expected: usize, found: usize },
+    /// Field of the uniform is misplaced, usually lacks padding before it
+    Offset { uniform: String, expected: usize, found: usize },
+    /// Struct size differs from the block size, usually lacks padding at the end
+    Size { expected: usize, found: usize },
+}
+
+impl Display for Std140Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for Std140Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Failure of [`crate::ShaderPreprocessor::process()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreprocessError {
+    /// `#include` of a file which was not added to the preprocessor
+    MissingInclude { name: String },
+    /// File includes itself, directly or through other files
+    IncludeCycle { name: String },
+    /// Malformed `#include` or `#version` directive
+    InvalidDirective(String),
+    /// `flat`, `noperspective` or `centroid` qualifier, which GLSL 100 has no equivalent of
+    UnsupportedQualifier(String),
+}
+
+impl Display for PreprocessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for PreprocessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -1,22 +1,264 @@
+use std::ffi::c_void;
+use std::os::raw::c_char;
 use std::rc::Rc;
+use glow::HasContext;
+use crate::{GlError, UniformType};
 
 /// The context required to interact with the GPU
 #[derive(Clone)]
 pub struct GlowContext(pub(crate) Rc<ContextContents>);
 
 pub(crate) struct ContextContents {
-    pub(crate) gl: glow::Context
+    pub(crate) gl: glow::Context,
+    pub(crate) raw: RawGlFns,
+    /// Object labels, debug groups and debug output of `KHR_debug` are available
+    pub(crate) khr_debug: bool,
+    /// Keeps the surfaceless EGL context alive for as long as anything may issue GL calls
+    #[cfg(feature = "headless")]
+    #[allow(dead_code)]
+    pub(crate) headless: Option<crate::headless::HeadlessEgl>
+}
+
+/// Entry points not exposed by `glow`, loaded with the same loader function.
+///
+/// All of them are `None` for contexts created from an already loaded `glow::Context`.
+#[derive(Default)]
+pub(crate) struct RawGlFns {
+    pub(crate) primitive_restart_index: Option<unsafe extern "system" fn(u32)>,
+    pub(crate) debug_message_callback: Option<unsafe extern "system" fn(Option<GlDebugProc>, *const c_void)>,
+    pub(crate) uniform_matrix_2x3: Option<UniformMatrixFn>,
+    pub(crate) uniform_matrix_2x4: Option<UniformMatrixFn>,
+    pub(crate) uniform_matrix_3x2: Option<UniformMatrixFn>,
+    pub(crate) uniform_matrix_3x4: Option<UniformMatrixFn>,
+    pub(crate) uniform_matrix_4x2: Option<UniformMatrixFn>,
+    pub(crate) uniform_matrix_4x3: Option<UniformMatrixFn>,
+    pub(crate) get_program_iv: Option<unsafe extern "system" fn(program: u32, pname: u32, params: *mut i32)>,
+    pub(crate) get_program_binary: Option<unsafe extern "system" fn(
+        program: u32,
+        buf_size: i32,
+        length: *mut i32,
+        binary_format: *mut u32,
+        binary: *mut c_void
+    )>,
+    pub(crate) program_binary: Option<unsafe extern "system" fn(
+        program: u32,
+        binary_format: u32,
+        binary: *const c_void,
+        length: i32
+    )>,
+    pub(crate) program_parameter_i: Option<unsafe extern "system" fn(program: u32, pname: u32, value: i32)>,
+}
+
+/// `glUniformMatrix{C}x{R}fv`
+pub(crate) type UniformMatrixFn = unsafe extern "system" fn(
+    location: i32,
+    count: i32,
+    transpose: u8,
+    value: *const f32
+);
+
+/// `GLDEBUGPROC`
+pub(crate) type GlDebugProc = extern "system" fn(
+    source: u32,
+    message_type: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    user_param: *mut c_void
+);
+
+impl RawGlFns {
+    unsafe fn load<F>(loader: &mut F) -> Self
+    where F: FnMut(&str) -> *const c_void
+    {
+        RawGlFns {
+            primitive_restart_index: load_fn(loader, &["glPrimitiveRestartIndex"]),
+            debug_message_callback: load_fn(loader, &[
+                "glDebugMessageCallback",
+                "glDebugMessageCallbackKHR",
+                "glDebugMessageCallbackARB"
+            ]),
+            uniform_matrix_2x3: load_fn(loader, &["glUniformMatrix2x3fv"]),
+            uniform_matrix_2x4: load_fn(loader, &["glUniformMatrix2x4fv"]),
+            uniform_matrix_3x2: load_fn(loader, &["glUniformMatrix3x2fv"]),
+            uniform_matrix_3x4: load_fn(loader, &["glUniformMatrix3x4fv"]),
+            uniform_matrix_4x2: load_fn(loader, &["glUniformMatrix4x2fv"]),
+            uniform_matrix_4x3: load_fn(loader, &["glUniformMatrix4x3fv"]),
+            get_program_iv: load_fn(loader, &["glGetProgramiv"]),
+            get_program_binary: load_fn(loader, &["glGetProgramBinary", "glGetProgramBinaryOES"]),
+            program_binary: load_fn(loader, &["glProgramBinary", "glProgramBinaryOES"]),
+            program_parameter_i: load_fn(loader, &["glProgramParameteri"]),
+        }
+    }
+}
+
+impl RawGlFns {
+    /// `glUniformMatrix{C}x{R}fv` uploading `uniform_type`, `None` for other types and entry points which are not loaded
+    pub(crate) fn uniform_matrix(&self, uniform_type: UniformType) -> Option<UniformMatrixFn> {
+        match uniform_type {
+            UniformType::Mat2x3 => self.uniform_matrix_2x3,
+            UniformType::Mat2x4 => self.uniform_matrix_2x4,
+            UniformType::Mat3x2 => self.uniform_matrix_3x2,
+            UniformType::Mat3x4 => self.uniform_matrix_3x4,
+            UniformType::Mat4x2 => self.uniform_matrix_4x2,
+            UniformType::Mat4x3 => self.uniform_matrix_4x3,
+            _ => None,
+        }
+    }
+}
+
+/// Loads the first of `names` the loader knows, `T` has to be a function pointer type
+unsafe fn load_fn<F, T>(loader: &mut F, names: &[&str]) -> Option<T>
+where F: FnMut(&str) -> *const c_void
+{
+    debug_assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<*const c_void>());
+
+    names
+        .iter()
+        .map(|name| loader(name))
+        .find(|ptr| !ptr.is_null())
+        .map(|ptr| std::mem::transmute_copy(&ptr))
 }
 
 impl GlowContext {
+    #[cfg(feature = "window")]
     pub(crate) fn new_from_sdl2_video(video: &sdl2::VideoSubsystem) -> Self {
+        unsafe {
+            Self::new_from_loader(|s| video.gl_get_proc_address(s) as *const _)
+        }
+    }
+
+    pub(crate) unsafe fn new_from_loader<F>(mut loader: F) -> Self
+    where F: FnMut(&str) -> *const c_void
+    {
+        let raw = RawGlFns::load(&mut loader);
+        let gl = glow::Context::from_loader_function(loader);
+        Self::new(gl, raw)
+    }
+
+    pub(crate) fn new_from_glow(gl: glow::Context) -> Self {
+        Self::new(gl, RawGlFns::default())
+    }
+
+    fn new(gl: glow::Context, raw: RawGlFns) -> Self {
+        let version = gl.version();
+        // Core since GL 4.3 and GLES 3.2
+        let khr_debug = gl.supported_extensions().contains("GL_KHR_debug")
+            || (!version.is_embedded && (version.major, version.minor) >= (4, 3))
+            || (version.is_embedded && (version.major, version.minor) >= (3, 2));
+
         GlowContext(Rc::new(ContextContents {
-            gl: unsafe {
-                let gl = glow::
-                Context::
-                from_loader_function(|s| video.gl_get_proc_address(s) as *const _);
-                gl
-            }
+            gl,
+            raw,
+            khr_debug,
+            #[cfg(feature = "headless")]
+            headless: None
         }))
     }
-}
\ No newline at end of file
+
+    /// Labels GL `object` of `identifier` type (`GL_BUFFER`, `GL_TEXTURE` etc.) for GPU captures,
+    /// does nothing without `KHR_debug`
+    pub(crate) fn object_label<T: Copy>(&self, identifier: u32, object: T, label: Option<&str>) {
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<u32>());
+
+        if self.0.khr_debug {
+            unsafe {
+                let name: u32 = std::mem::transmute_copy(&object);
+                self.0.gl.object_label(identifier, name, label);
+            }
+        }
+    }
+
+    /// `glGetProgramiv`, `None` for contexts created from an already loaded `glow::Context`
+    pub(crate) fn get_program_parameter(&self, program: glow::Program, parameter: u32) -> Option<i32> {
+        let get_program_iv = self.0.raw.get_program_iv?;
+        let mut value = 0;
+        unsafe {
+            let program: u32 = std::mem::transmute_copy(&program);
+            get_program_iv(program, parameter, &mut value);
+        }
+        Some(value)
+    }
+
+    /// Drivers can return binaries of linked programs and load them back,
+    /// `false` for contexts created from an already loaded `glow::Context`
+    pub(crate) fn supports_program_binaries(&self) -> bool {
+        let raw = &self.0.raw;
+        let gl = &self.0.gl;
+        let version = gl.version();
+        // Entry points may be exported by drivers which don't support them for the context,
+        // while querying the formats there would leave GL_INVALID_ENUM behind
+        let supported = if version.is_embedded {
+            version.major >= 3 || gl.supported_extensions().contains("GL_OES_get_program_binary")
+        } else {
+            (version.major, version.minor) >= (4, 1) || gl.supported_extensions().contains("GL_ARB_get_program_binary")
+        };
+
+        supported
+            && raw.get_program_iv.is_some()
+            && raw.get_program_binary.is_some()
+            && raw.program_binary.is_some()
+            && unsafe { gl.get_parameter_i32(glow::NUM_PROGRAM_BINARY_FORMATS) } > 0
+    }
+
+    /// Asks the driver to keep the binary of `program` retrievable, should be called before linking
+    pub(crate) fn set_program_binary_retrievable(&self, program: glow::Program) {
+        if let Some(program_parameter_i) = self.0.raw.program_parameter_i {
+            unsafe {
+                let program: u32 = std::mem::transmute_copy(&program);
+                program_parameter_i(program, glow::PROGRAM_BINARY_RETRIEVABLE_HINT, 1);
+            }
+        }
+    }
+
+    /// `glGetProgramBinary`, returns the binary format and data of a linked `program`
+    pub(crate) fn get_program_binary(&self, program: glow::Program) -> Option<(u32, Vec<u8>)> {
+        let get_program_binary = self.0.raw.get_program_binary?;
+        let length = self.get_program_parameter(program, glow::PROGRAM_BINARY_LENGTH)?;
+        if length <= 0 {
+            return None;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            let program: u32 = std::mem::transmute_copy(&program);
+            get_program_binary(program, length, &mut written, &mut format, binary.as_mut_ptr() as *mut c_void);
+        }
+        binary.truncate(written.max(0) as usize);
+
+        if binary.is_empty() {
+            None
+        } else {
+            Some((format, binary))
+        }
+    }
+
+    /// `glProgramBinary`, reports whether the driver accepted the binary and `program` got linked
+    pub(crate) fn program_binary(&self, program: glow::Program, format: u32, binary: &[u8]) -> bool {
+        let program_binary = match self.0.raw.program_binary {
+            Some(program_binary) => program_binary,
+            None => return false,
+        };
+
+        let gl = &self.0.gl;
+        unsafe {
+            GlError::clear(gl);
+            let name: u32 = std::mem::transmute_copy(&program);
+            program_binary(name, format, binary.as_ptr() as *const c_void, binary.len() as i32);
+            // Unsupported formats are reported with GL_INVALID_ENUM, outdated binaries with a link failure
+            GlError::check(gl).is_ok() && gl.get_program_link_status(program)
+        }
+    }
+
+    #[cfg(feature = "headless")]
+    pub(crate) fn new_from_headless_egl(headless: crate::headless::HeadlessEgl) -> Self {
+        let mut glow_ctx = unsafe {
+            Self::new_from_loader(|s| headless.get_proc_address(s))
+        };
+        Rc::get_mut(&mut glow_ctx.0).unwrap().headless = Some(headless);
+        glow_ctx
+    }
+}
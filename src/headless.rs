@@ -0,0 +1,212 @@
+use std::error::Error;
+use std::fmt::Display;
+use glow::HasContext;
+use khronos_egl as egl;
+use crate::{Context, Texture, TextureParams};
+use crate::glow_context::GlowContext;
+
+/// `EGL_PLATFORM_SURFACELESS_MESA` from `EGL_MESA_platform_surfaceless`
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+#[derive(Clone, Debug)]
+pub enum HeadlessError {
+    /// libEGL could not be loaded or does not provide EGL 1.4
+    LibraryLoad(String),
+    /// No EGL display is available, neither surfaceless nor default one
+    NoDisplay,
+    /// The display does not expose a config usable for desktop OpenGL rendering
+    NoConfig,
+    /// EGL call failed
+    Egl(egl::Error),
+}
+
+impl From<egl::Error> for HeadlessError {
+    fn from(e: egl::Error) -> HeadlessError {
+        HeadlessError::Egl(e)
+    }
+}
+
+impl Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for HeadlessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Owns the EGL display and the surfaceless context the `glow` context was loaded from.
+pub(crate) struct HeadlessEgl {
+    egl: egl::DynamicInstance<egl::EGL1_4>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl HeadlessEgl {
+    pub(crate) fn new() -> Result<Self, HeadlessError> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_4>::load_required() }
+            .map_err(|e| HeadlessError::LibraryLoad(e.to_string()))?;
+
+        let display = Self::get_display(&egl).ok_or(HeadlessError::NoDisplay)?;
+        egl.initialize(display)?;
+        egl.bind_api(egl::OPENGL_API)?;
+
+        let config = egl
+            .choose_first_config(display, &[
+                egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+                egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+                egl::NONE
+            ])?
+            .ok_or(HeadlessError::NoConfig)?;
+
+        let context = egl.create_context(display, config, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 2,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE
+        ])?;
+
+        // No surfaces at all: rendering goes exclusively into framebuffer objects
+        egl.make_current(display, None, None, Some(context))?;
+
+        Ok(Self { egl, display, context })
+    }
+
+    pub(crate) fn get_proc_address(&self, symbol: &str) -> *const std::ffi::c_void {
+        self.egl
+            .get_proc_address(symbol)
+            .map_or(std::ptr::null(), |f| f as *const _)
+    }
+
+    fn get_display(egl: &egl::DynamicInstance<egl::EGL1_4>) -> Option<egl::Display> {
+        let supports_surfaceless = egl
+            .query_string(None, egl::EXTENSIONS)
+            .map(|extensions| extensions
+                .to_string_lossy()
+                .split(' ')
+                .any(|ext| ext == "EGL_MESA_platform_surfaceless")
+            )
+            .unwrap_or(false);
+
+        if supports_surfaceless {
+            if let Some(egl15) = egl.upcast::<egl::EGL1_5>() {
+                let display = egl15.get_platform_display(
+                    PLATFORM_SURFACELESS_MESA,
+                    egl::DEFAULT_DISPLAY,
+                    &[egl::ATTRIB_NONE]
+                );
+                if let Ok(display) = display {
+                    return Some(display);
+                }
+            }
+        }
+
+        egl.get_display(egl::DEFAULT_DISPLAY)
+    }
+}
+
+impl Drop for HeadlessEgl {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
+
+/// Framebuffer standing in for the window one when there is no window at all.
+pub(crate) struct OffscreenTarget {
+    gl_fb: glow::Framebuffer,
+    texture: Texture,
+    depth_stencil: glow::Renderbuffer,
+}
+
+impl OffscreenTarget {
+    fn new(ctx: &mut Context, width: i32, height: i32) -> Self {
+        let texture = Texture::new_render_texture(ctx, TextureParams {
+            width: width as _,
+            height: height as _,
+            ..Default::default()
+        });
+
+        let gl = &ctx.glow_ctx.0.gl;
+
+        unsafe {
+            let depth_stencil = gl.create_renderbuffer().unwrap();
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth_stencil));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width, height);
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+            let gl_fb = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(gl_fb));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                texture.texture,
+                0
+            );
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_STENCIL_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth_stencil)
+            );
+
+            Self { gl_fb, texture, depth_stencil }
+        }
+    }
+
+    pub(crate) fn texture(&self) -> Texture {
+        self.texture.clone()
+    }
+
+    pub(crate) fn resize(&mut self, ctx: &mut Context, width: i32, height: i32) {
+        self.texture.resize(ctx, width as _, height as _, None);
+
+        let gl = &ctx.glow_ctx.0.gl;
+        unsafe {
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(self.depth_stencil));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width, height);
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+        }
+    }
+
+    pub(crate) fn delete(&self, ctx: &mut Context) {
+        let gl = &ctx.glow_ctx.0.gl;
+        unsafe {
+            gl.delete_framebuffer(self.gl_fb);
+            gl.delete_renderbuffer(self.depth_stencil);
+        }
+        self.texture.delete();
+    }
+}
+
+impl Context {
+    /// Creates a context without any window, backed by a surfaceless EGL context
+    /// (e.g. Mesa's llvmpipe on a GPU-less machine).
+    ///
+    /// The default pass renders into an offscreen framebuffer of `width` x `height` pixels,
+    /// its color attachment is available through [`Context::default_framebuffer_texture()`].
+    pub fn new_headless(width: i32, height: i32) -> Result<Self, HeadlessError> {
+        let headless = HeadlessEgl::new()?;
+        let glow_ctx = GlowContext::new_from_headless_egl(headless);
+
+        let mut ctx = Self::new_impl(&glow_ctx, width, height);
+
+        let target = OffscreenTarget::new(&mut ctx, width, height);
+        ctx.default_framebuffer = target.gl_fb;
+        ctx.offscreen_target = Some(target);
+
+        Ok(ctx)
+    }
+
+    /// Color attachment of the offscreen default framebuffer of a headless context.
+    ///
+    /// Returns `None` if the context is attached to a window.
+    pub fn default_framebuffer_texture(&self) -> Option<Texture> {
+        self.offscreen_target.as_ref().map(|target| target.texture())
+    }
+}
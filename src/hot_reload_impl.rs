@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use glow::HasContext;
+use crate::{vertex_layout, Context, Shader, ShaderError, ShaderMeta, ShaderPreprocessor, ShaderType};
+use crate::shader_impl::{check_uniform_types, link_program, shader_internal_from_program};
+
+/// Where GLSL code of a shader stage comes from, see [`Shader::from_sources()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShaderSource {
+    Code(String),
+    /// File which is read on creation and read again whenever its modification time changes
+    File(PathBuf),
+}
+
+impl ShaderSource {
+    fn read(&self) -> Result<String, ShaderError> {
+        match self {
+            ShaderSource::Code(code) => Ok(code.clone()),
+            ShaderSource::File(path) => std::fs::read_to_string(path).map_err(|e| ShaderError::ReadError {
+                path: path.clone(),
+                error_message: e.to_string(),
+            }),
+        }
+    }
+
+    /// `None` for code and for files which can't be accessed at the moment, e.g. in the middle of a save
+    fn modified(&self) -> Option<SystemTime> {
+        match self {
+            ShaderSource::Code(_) => None,
+            ShaderSource::File(path) => std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok(),
+        }
+    }
+}
+
+impl From<&str> for ShaderSource {
+    fn from(code: &str) -> ShaderSource {
+        ShaderSource::Code(code.to_string())
+    }
+}
+
+impl From<String> for ShaderSource {
+    fn from(code: String) -> ShaderSource {
+        ShaderSource::Code(code)
+    }
+}
+
+impl From<&Path> for ShaderSource {
+    fn from(path: &Path) -> ShaderSource {
+        ShaderSource::File(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for ShaderSource {
+    fn from(path: PathBuf) -> ShaderSource {
+        ShaderSource::File(path)
+    }
+}
+
+/// Sources of a shader with at least one file among them, everything needed to rebuild it.
+pub(crate) struct ShaderWatch {
+    vertex: ShaderSource,
+    fragment: ShaderSource,
+    meta: ShaderMeta,
+    preprocessor: Option<ShaderPreprocessor>,
+    modified: [Option<SystemTime>; 2],
+}
+
+impl ShaderWatch {
+    fn current_modified(&self) -> [Option<SystemTime>; 2] {
+        [self.vertex.modified(), self.fragment.modified()]
+    }
+
+    /// Reads both sources and runs them through the preprocessor, if any
+    fn read(&self) -> Result<(String, String), ShaderError> {
+        let vertex = self.vertex.read()?;
+        let fragment = self.fragment.read()?;
+        match &self.preprocessor {
+            Some(preprocessor) => Ok((
+                preprocessor.process(ShaderType::Vertex, &vertex)?,
+                preprocessor.process(ShaderType::Fragment, &fragment)?,
+            )),
+            None => Ok((vertex, fragment)),
+        }
+    }
+}
+
+impl Shader {
+    /// Same as [`Shader::new()`], with sources which may be files.
+    ///
+    /// Shaders with file sources are watched: [`Context::commit_frame()`] compares modification
+    /// times of the files and rebuilds the program when any of them changes. Pipelines created
+    /// with the shader keep working and use the new program after it gets applied.
+    pub fn from_sources(
+        ctx: &mut Context,
+        vertex_shader: ShaderSource,
+        fragment_shader: ShaderSource,
+        meta: ShaderMeta,
+    ) -> Result<Shader, ShaderError> {
+        Self::from_sources_impl(ctx, vertex_shader, fragment_shader, meta, None)
+    }
+
+    /// Same as [`Shader::from_sources()`], with both sources run through `preprocessor`
+    /// on creation and on every reload.
+    ///
+    /// Only the stage files themselves are watched: files added to the preprocessor with
+    /// [`ShaderPreprocessor::add_file()`] are fixed, so changing an included file takes
+    /// a new preprocessor and a new shader.
+    pub fn from_sources_with_preprocessor(
+        ctx: &mut Context,
+        vertex_shader: ShaderSource,
+        fragment_shader: ShaderSource,
+        meta: ShaderMeta,
+        preprocessor: ShaderPreprocessor,
+    ) -> Result<Shader, ShaderError> {
+        Self::from_sources_impl(ctx, vertex_shader, fragment_shader, meta, Some(preprocessor))
+    }
+
+    fn from_sources_impl(
+        ctx: &mut Context,
+        vertex_shader: ShaderSource,
+        fragment_shader: ShaderSource,
+        meta: ShaderMeta,
+        preprocessor: Option<ShaderPreprocessor>,
+    ) -> Result<Shader, ShaderError> {
+        let mut watch = ShaderWatch {
+            vertex: vertex_shader,
+            fragment: fragment_shader,
+            meta,
+            preprocessor,
+            modified: [None; 2],
+        };
+        // Taken before reading, so changes made in between are picked up by the next check
+        watch.modified = watch.current_modified();
+        check_uniform_types(ctx, &watch.meta)?;
+
+        let (vertex, fragment) = watch.read()?;
+        let program = link_program(ctx, &vertex, &fragment)?;
+        let mut shader = shader_internal_from_program(ctx, program, &watch.meta);
+
+        let is_file = |source: &ShaderSource| matches!(source, ShaderSource::File(_));
+        if is_file(&watch.vertex) || is_file(&watch.fragment) {
+            shader.watch = Some(Box::new(watch));
+        }
+
+        Ok(Shader(ctx.shaders.insert(shader)))
+    }
+}
+
+impl Context {
+    /// Returns errors of shader reloads since the previous call, in the order they happened.
+    ///
+    /// A shader which failed to reload keeps its previous program and is reloaded again
+    /// on the next change of its files.
+    pub fn take_shader_reload_errors(&mut self) -> Vec<(Shader, ShaderError)> {
+        std::mem::take(&mut self.shader_reload_errors)
+    }
+
+    /// Rebuilds watched shaders whose files changed since the previous check
+    pub(crate) fn reload_changed_shaders(&mut self) {
+        let changed: Vec<Shader> = self
+            .shaders
+            .iter()
+            .filter(|(_, shader)| {
+                shader.watch.as_ref().is_some_and(|watch| watch.modified != watch.current_modified())
+            })
+            .map(|(key, _)| Shader(key))
+            .collect();
+
+        for shader in changed {
+            let mut watch = self.shaders.get_mut(shader.0).and_then(|shader| shader.watch.take()).unwrap();
+            watch.modified = watch.current_modified();
+
+            if let Err(e) = self.reload_shader(shader, &watch) {
+                self.shader_reload_errors.push((shader, e));
+            }
+            self.shaders.get_mut(shader.0).unwrap().watch = Some(watch);
+        }
+    }
+
+    fn reload_shader(&mut self, shader: Shader, watch: &ShaderWatch) -> Result<(), ShaderError> {
+        let (vertex, fragment) = watch.read()?;
+        let program = link_program(self, &vertex, &fragment)?;
+
+        // Attribute locations may move around, so every pipeline of the shader gets its layout rebuilt
+        let mut layouts = vec![];
+        for (key, pipeline) in self.pipelines.iter().filter(|(_, pipeline)| pipeline.shader == shader) {
+            match vertex_layout(self, &pipeline.buffer_layout, &pipeline.attributes, program) {
+                Ok(layout) => layouts.push((key, layout)),
+                Err(e) => {
+                    unsafe {
+                        self.glow_ctx.0.gl.delete_program(program);
+                    }
+                    return Err(ShaderError::IncompatiblePipeline(e));
+                }
+            }
+        }
+        for (key, layout) in layouts {
+            self.pipelines.get_mut(key).unwrap().layout = layout;
+        }
+
+        let mut new_shader = shader_internal_from_program(self, program, &watch.meta);
+        let label = self.shaders.get_mut(shader.0).unwrap().label.take();
+        self.glow_ctx.object_label(glow::PROGRAM, program, label.as_deref());
+        new_shader.label = label;
+        let old_shader = std::mem::replace(self.shaders.get_mut(shader.0).unwrap(), new_shader);
+        old_shader.delete(self);
+
+        // `shader_internal_from_program` leaves the new program in use
+        let current_program = self
+            .cache
+            .cur_pipeline
+            .map(|pipeline| self.pipeline_internal(pipeline).shader)
+            .and_then(|current| self.shaders.get(current.0))
+            .map(|current| current.program);
+        unsafe {
+            self.glow_ctx.0.gl.use_program(current_program);
+        }
+
+        Ok(())
+    }
+}
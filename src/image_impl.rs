@@ -0,0 +1,49 @@
+/// CPU side RGBA8 image, rows go from top to bottom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl Image {
+    /// Flips rows of bottom-up `bytes` as they come out of `glReadPixels`
+    pub(crate) fn from_gl_rows(width: u32, height: u32, mut bytes: Vec<u8>) -> Image {
+        let row_size = width as usize * 4;
+        if let Some(rows) = bytes.len().checked_div(row_size) {
+            for y in 0..rows / 2 {
+                let (top, bottom) = bytes.split_at_mut((rows - 1 - y) * row_size);
+                top[y * row_size..(y + 1) * row_size].swap_with_slice(&mut bottom[..row_size]);
+            }
+        }
+
+        Image { width, height, bytes }
+    }
+
+    /// RGBA value of the pixel in column `x` and row `y`, counting from the top left corner.
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.width && y < self.height, "Pixel ({}, {}) is out of image bounds", x, y);
+
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.bytes[offset..offset + 4]);
+        pixel
+    }
+
+    /// Encodes the image as PNG into `writer`.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.bytes)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), png::EncodingError> {
+        let file = std::fs::File::create(path)?;
+        self.write_png(std::io::BufWriter::new(file))
+    }
+}
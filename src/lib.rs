@@ -1,4 +1,5 @@
 
+use std::cell::RefCell;
 use std::num::NonZeroU32;
 use glow::{HasContext};
 use crate::cache_impl::GlCache;
@@ -10,42 +11,116 @@ mod shader_impl;
 mod types_impl;
 mod query_impl;
 mod buffer_impl;
+mod renderbuffer_impl;
+mod image_impl;
+mod error_impl;
+mod debug_impl;
+mod command_list_impl;
+mod validation_impl;
+mod reflection_impl;
+mod hot_reload_impl;
+mod preprocessor_impl;
+mod shader_library_impl;
+mod program_cache_impl;
 mod cache_impl;
+mod slot_map;
+#[cfg(feature = "headless")]
+mod headless;
 
+#[cfg(feature = "window")]
 pub mod window;
+#[cfg(feature = "egui")]
 pub mod egui_integration;
 
+pub use glow;
+#[cfg(feature = "derive")]
+pub use gl_pipelines_derive::{Uniforms, VertexLayout};
+
 pub use texture::{FilterMode, Texture, TextureAccess, TextureFormat, TextureParams, TextureWrap, TextureKind};
 pub use shader_impl::{Shader, ShaderMeta, ShaderImage, ShaderUniform, ShaderType, ShaderError};
 pub use types_impl::{
     UniformType, UniformDesc, UniformBlockLayout, VertexFormat, VertexStep, BufferLayout,
     VertexAttribute, PipelineLayout, BlendState, StencilState, StencilFaceState, StencilOp, CompareFunc,
     Equation, BlendValue, BlendFactor, CullFace, FrontFaceOrder, Comparison, PrimitiveType, IndexType,
-    BufferType, Usage
+    BufferType, Usage, BlitMask, Uniforms, VertexLayout
 };
 pub use query_impl::*;
 pub use buffer_impl::*;
+pub use renderbuffer_impl::{Attachment, FramebufferError, Renderbuffer};
+pub use image_impl::Image;
+pub use error_impl::{GlError, PipelineError, PreprocessError, Std140Error};
+pub use debug_impl::{DebugMessage, DebugMode, DebugOutput, DebugSeverity};
+pub use command_list_impl::{Command, CommandList, RawBindings};
+pub use validation_impl::ValidationError;
+pub use reflection_impl::{ReflectedAttribute, ReflectedUniform, ReflectedUniformBlock, ShaderReflection};
+pub use hot_reload_impl::ShaderSource;
+pub use preprocessor_impl::{GlslVersion, ShaderPreprocessor};
+pub use shader_library_impl::{ShaderLibrary, ShaderLibraryStats};
+pub use program_cache_impl::{ProgramBinaryCache, ProgramBinaryCacheStats};
+#[cfg(feature = "headless")]
+pub use headless::HeadlessError;
 use crate::shader_impl::ShaderInternal;
+use crate::command_list_impl::BindingsSource;
+use crate::slot_map::{SlotKey, SlotMap, stale_handle};
+use crate::debug_impl::DebugState;
+use crate::validation_impl::ValidationState;
 
 pub const MAX_VERTEX_ATTRIBUTES: usize = 16;
 pub const MAX_SHADERSTAGE_IMAGES: usize = 12;
+pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+pub const MAX_SHADERSTAGE_UNIFORM_BLOCKS: usize = 12;
 
 pub struct Context {
     window_size: (i32, i32),
     dpi: (f32, f32),
-    shaders: Vec<ShaderInternal>,
-    pipelines: Vec<PipelineInternal>,
-    passes: Vec<RenderPassInternal>,
+    shaders: SlotMap<ShaderInternal>,
+    pipelines: SlotMap<PipelineInternal>,
+    passes: SlotMap<RenderPassInternal>,
+    current_pass: Option<RenderPass>,
+    /// Debug group of the current pass is open
+    pass_debug_group: bool,
     default_framebuffer: glow::Framebuffer,
     cache: GlCache,
+    #[cfg(feature = "headless")]
+    offscreen_target: Option<headless::OffscreenTarget>,
+    debug: Option<Box<RefCell<DebugState>>>,
+    validation: ValidationState,
+    /// Failed reloads of watched shaders, see `Context::take_shader_reload_errors`
+    shader_reload_errors: Vec<(Shader, ShaderError)>,
+    program_cache: Option<ProgramBinaryCache>,
     glow_ctx: GlowContext
 }
 
 impl Context {
+    #[cfg(feature = "window")]
     pub fn new_from_sdl2(video: &sdl2::VideoSubsystem, default_w: i32, default_h: i32) -> Self {
         Self::new_impl(&GlowContext::new_from_sdl2_video(video), default_w, default_h)
     }
 
+    /// Wraps a GL context owned by the host application (glutin, GTK GLArea, Qt etc.).
+    ///
+    /// The context should be current on the calling thread. Framebuffer bound at the moment
+    /// of the call is treated as the default one.
+    ///
+    /// Entry points `glow` doesn't expose can't be loaded this way, so primitive restart
+    /// falls back to the fixed index, debug output to error polling, and shaders with
+    /// non-square matrix uniforms fail to create. Use [`Context::from_loader()`] to have them all.
+    pub fn from_glow(gl: glow::Context, default_w: i32, default_h: i32) -> Self {
+        Self::new_impl(&GlowContext::new_from_glow(gl), default_w, default_h)
+    }
+
+    /// Same as [`Context::from_glow()`], but loads GL functions with `loader` first.
+    ///
+    /// # Safety
+    ///
+    /// `loader` should return valid function pointers (or null) of the GL context current
+    /// on the calling thread.
+    pub unsafe fn from_loader<F>(loader: F, default_w: i32, default_h: i32) -> Self
+    where F: FnMut(&str) -> *const std::ffi::c_void
+    {
+        Self::new_impl(&GlowContext::new_from_loader(loader), default_w, default_h)
+    }
+
     fn new_impl(glow_ctx: &GlowContext, default_w: i32, default_h: i32) -> Self {
         let glow_ctx = glow_ctx.clone();
         let glow_ctx2 = glow_ctx.clone();
@@ -60,29 +135,51 @@ impl Context {
         unsafe {
             let vao = gl.create_vertex_array().unwrap();
             gl.bind_vertex_array(Some(vao));
+
+            // Always on in GLES, needed for gl_PointSize to take effect on desktop GL
+            if !gl.version().is_embedded {
+                gl.enable(glow::PROGRAM_POINT_SIZE);
+            }
         }
 
         Context {
             window_size: (default_w, default_h),
             dpi: (1.0, 1.0),
             default_framebuffer,
-            pipelines: Vec::new(),
-            passes: Vec::new(),
-            shaders: Vec::new(),
+            pipelines: SlotMap::new(),
+            passes: SlotMap::new(),
+            current_pass: None,
+            pass_debug_group: false,
+            shaders: SlotMap::new(),
+            #[cfg(feature = "headless")]
+            offscreen_target: None,
+            debug: None,
+            validation: ValidationState::default(),
+            shader_reload_errors: vec![],
+            program_cache: None,
             glow_ctx,
             cache: GlCache {
                 glow_ctx: glow_ctx2,
                 stored_index_buffer: None,
                 stored_index_type: None,
                 stored_vertex_buffer: None,
+                stored_uniform_buffer: None,
                 index_buffer: None,
                 index_type: None,
                 vertex_buffer: None,
+                uniform_buffer: None,
+                uniform_buffers: [None; MAX_SHADERSTAGE_UNIFORM_BLOCKS],
                 color_blend: None,
                 alpha_blend: None,
                 stencil: None,
                 color_write: (true, true, true, true),
                 cull_face: CullFace::Nothing,
+                primitive_restart: None,
+                depth_write_offset: None,
+                depth_test: None,
+                depth_write: true,
+                depth_range: (0.0, 1.0),
+                depth_clamp: false,
                 stored_texture: None,
                 textures: [None; MAX_SHADERSTAGE_IMAGES],
                 cur_pipeline: None,
@@ -91,8 +188,26 @@ impl Context {
         }
     }
 
+    fn pipeline_internal(&self, pipeline: Pipeline) -> &PipelineInternal {
+        self.pipelines.get(pipeline.0).unwrap_or_else(|| stale_handle(pipeline))
+    }
+
+    fn shader_internal(&self, shader: Shader) -> &ShaderInternal {
+        self.shaders.get(shader.0).unwrap_or_else(|| stale_handle(shader))
+    }
+
+    fn pass_internal(&self, pass: RenderPass) -> &RenderPassInternal {
+        self.passes.get(pass.0).unwrap_or_else(|| stale_handle(pass))
+    }
+
     pub fn update_window_size(&mut self, w: i32, h: i32) {
         self.window_size = (w, h);
+
+        #[cfg(feature = "headless")]
+        if let Some(mut target) = self.offscreen_target.take() {
+            target.resize(self, w, h);
+            self.offscreen_target = Some(target);
+        }
     }
 
     pub fn get_window_size(&self) -> (i32, i32) {
@@ -108,31 +223,36 @@ impl Context {
     }
 
     pub fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        let params = self.pipeline_internal(*pipeline).params;
         self.cache.cur_pipeline = Some(*pipeline);
+        if self.validation.enabled {
+            self.validation.pipeline_applied();
+        }
         let gl = &self.glow_ctx.0.gl;
 
         {
-            let pipeline = &self.pipelines[pipeline.0];
-            let shader = &mut self.shaders[pipeline.shader.0];
+            let pipeline = self.pipeline_internal(*pipeline);
+            let shader = self.shader_internal(pipeline.shader);
             unsafe {
                 gl.use_program(Some(shader.program));
             }
 
-            unsafe {
-                gl.enable(glow::SCISSOR_TEST);
-            }
-
-            if pipeline.params.depth_write {
-                unsafe {
-                    gl.enable(glow::DEPTH_TEST);
-                    gl.depth_func(pipeline.params.depth_test.into())
-                }
-            } else {
+            if let (Some(label), true) = (&pipeline.label, self.glow_ctx.0.khr_debug) {
                 unsafe {
-                    gl.disable(glow::DEPTH_TEST);
+                    gl.debug_message_insert(
+                        glow::DEBUG_SOURCE_APPLICATION,
+                        glow::DEBUG_TYPE_MARKER,
+                        0,
+                        glow::DEBUG_SEVERITY_NOTIFICATION,
+                        label
+                    );
                 }
             }
 
+            unsafe {
+                gl.enable(glow::SCISSOR_TEST);
+            }
+
             match pipeline.params.front_face_order {
                 FrontFaceOrder::Clockwise => unsafe {
                     gl.front_face(glow::CW);
@@ -143,14 +263,24 @@ impl Context {
             }
         }
 
-        self.set_cull_face(self.pipelines[pipeline.0].params.cull_face);
-        self.set_blend(
-            self.pipelines[pipeline.0].params.color_blend,
-            self.pipelines[pipeline.0].params.alpha_blend,
-        );
+        self.set_cull_face(params.cull_face);
+        self.set_blend(params.color_blend, params.alpha_blend);
+
+        self.set_stencil(params.stencil_test);
+        self.set_color_write(params.color_write);
+        // Checked by `Pipeline::try_with_params`
+        let _ = self.set_primitive_restart(params.primitive_restart);
+        self.set_depth_write_offset(params.depth_write_offset);
+
+        // Writes are only performed with depth test enabled, hence "write without test"
+        // is expressed as a test which always passes
+        let depth_test_enabled = params.depth_test != Comparison::Always || params.depth_write;
+        self.set_depth_test(if depth_test_enabled { Some(params.depth_test) } else { None });
+        self.set_depth_write(params.depth_write);
+        self.set_depth_range(params.depth_range.0, params.depth_range.1);
+        self.set_depth_clamp(params.depth_clamp);
 
-        self.set_stencil(self.pipelines[pipeline.0].params.stencil_test);
-        self.set_color_write(self.pipelines[pipeline.0].params.color_write);
+        self.poll_gl_errors("Context::apply_pipeline");
     }
 
     pub fn set_cull_face(&mut self, cull_face: CullFace) {
@@ -176,6 +306,166 @@ impl Context {
         self.cache.cull_face = cull_face;
     }
 
+    /// Enables primitive restart with given restart index for indexed draws.
+    ///
+    /// Arbitrary restart indices rely on `glPrimitiveRestartIndex`, which is missing in GLES and
+    /// in contexts created with [`Context::from_glow()`]. There only the fixed restart index works,
+    /// i.e. the maximum value of the index type (`0xFFFF` for `u16` indices), which needs GL 4.3
+    /// or GLES 3. Other indices are rejected, leaving primitive restart as it was. As GL restarts
+    /// at the maximum value of whichever index type is bound, with validation on indexed draws
+    /// check that `restart_index` matches it, see [`ValidationError::PrimitiveRestartMismatch`].
+    pub fn set_primitive_restart(&mut self, restart_index: Option<u32>) -> Result<(), PipelineError> {
+        if self.cache.primitive_restart == restart_index {
+            return Ok(());
+        }
+        if let Some(restart_index) = restart_index {
+            self.check_primitive_restart(restart_index)?;
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+        let primitive_restart_index = self.glow_ctx.0.raw.primitive_restart_index
+            .filter(|_| !self.fixed_restart_index());
+
+        unsafe {
+            match (restart_index, primitive_restart_index) {
+                (Some(restart_index), Some(primitive_restart_index)) => {
+                    gl.enable(glow::PRIMITIVE_RESTART);
+                    primitive_restart_index(restart_index);
+                }
+                (Some(_), None) => {
+                    gl.enable(glow::PRIMITIVE_RESTART_FIXED_INDEX);
+                }
+                (None, Some(_)) => {
+                    gl.disable(glow::PRIMITIVE_RESTART);
+                }
+                (None, None) => {
+                    gl.disable(glow::PRIMITIVE_RESTART_FIXED_INDEX);
+                }
+            }
+        }
+        self.cache.primitive_restart = restart_index;
+
+        self.poll_gl_errors("Context::set_primitive_restart");
+        Ok(())
+    }
+
+    /// Whether primitive restart goes through `PRIMITIVE_RESTART_FIXED_INDEX`, restarting at the
+    /// maximum value of the bound index type instead of the set restart index
+    pub(crate) fn fixed_restart_index(&self) -> bool {
+        self.glow_ctx.0.raw.primitive_restart_index.is_none() || self.glow_ctx.0.gl.version().is_embedded
+    }
+
+    /// Checks that `restart_index` can be set with [`Context::set_primitive_restart()`]
+    fn check_primitive_restart(&self, restart_index: u32) -> Result<(), PipelineError> {
+        if !self.fixed_restart_index() {
+            return Ok(());
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+        let version = gl.version();
+
+        let fixed_index = matches!(restart_index, 0xFF | 0xFFFF | 0xFFFF_FFFF);
+        let has_fixed_index = if version.is_embedded {
+            version.major >= 3
+        } else {
+            (version.major, version.minor) >= (4, 3) || gl.supported_extensions().contains("GL_ARB_ES3_compatibility")
+        };
+        if fixed_index && has_fixed_index {
+            Ok(())
+        } else {
+            Err(PipelineError::UnsupportedPrimitiveRestart { restart_index })
+        }
+    }
+
+    /// Offsets depth values of filled polygons by `factor * DZ + r * units`,
+    /// see `glPolygonOffset` for details.
+    pub fn set_depth_write_offset(&mut self, depth_write_offset: Option<(f32, f32)>) {
+        if self.cache.depth_write_offset == depth_write_offset {
+            return;
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+
+        unsafe {
+            if let Some((factor, units)) = depth_write_offset {
+                if self.cache.depth_write_offset.is_none() {
+                    gl.enable(glow::POLYGON_OFFSET_FILL);
+                }
+                gl.polygon_offset(factor, units);
+            } else {
+                gl.disable(glow::POLYGON_OFFSET_FILL);
+            }
+        }
+        self.cache.depth_write_offset = depth_write_offset;
+    }
+
+    /// Enables depth test with given compare function, `None` disables the test
+    /// (and depth writes along with it).
+    pub fn set_depth_test(&mut self, depth_test: Option<Comparison>) {
+        if self.cache.depth_test == depth_test {
+            return;
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+
+        unsafe {
+            if let Some(depth_test) = depth_test {
+                if self.cache.depth_test.is_none() {
+                    gl.enable(glow::DEPTH_TEST);
+                }
+                gl.depth_func(depth_test.into());
+            } else {
+                gl.disable(glow::DEPTH_TEST);
+            }
+        }
+        self.cache.depth_test = depth_test;
+    }
+
+    pub fn set_depth_write(&mut self, depth_write: bool) {
+        if self.cache.depth_write == depth_write {
+            return;
+        }
+        unsafe {
+            self.glow_ctx.0.gl.depth_mask(depth_write);
+        }
+        self.cache.depth_write = depth_write;
+    }
+
+    /// Maps normalized device depth to window depth range `[near, far]`.
+    pub fn set_depth_range(&mut self, near: f32, far: f32) {
+        if self.cache.depth_range == (near, far) {
+            return;
+        }
+        unsafe {
+            self.glow_ctx.0.gl.depth_range_f32(near, far);
+        }
+        self.cache.depth_range = (near, far);
+    }
+
+    /// Clamps fragment depth instead of clipping primitives against near and far planes.
+    ///
+    /// Not available in GLES, where the call is ignored.
+    pub fn set_depth_clamp(&mut self, depth_clamp: bool) {
+        if self.cache.depth_clamp == depth_clamp {
+            return;
+        }
+
+        let gl = &self.glow_ctx.0.gl;
+
+        if gl.version().is_embedded {
+            return;
+        }
+
+        unsafe {
+            if depth_clamp {
+                gl.enable(glow::DEPTH_CLAMP);
+            } else {
+                gl.disable(glow::DEPTH_CLAMP);
+            }
+        }
+        self.cache.depth_clamp = depth_clamp;
+    }
+
     pub fn set_color_write(&mut self, color_write: ColorMask) {
         if self.cache.color_write == color_write {
             return;
@@ -289,40 +579,70 @@ impl Context {
         unsafe {
             self.glow_ctx.0.gl.viewport(x, y, w, h);
         }
+
+        self.poll_gl_errors("Context::apply_viewport");
     }
 
     pub fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
         unsafe {
             self.glow_ctx.0.gl.scissor(x, y, w, h);
         }
+
+        self.poll_gl_errors("Context::apply_scissor_rect");
     }
 
     pub fn apply_bindings(&mut self, bindings: &Bindings) {
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
-        let shader = &self.shaders[pip.shader.0];
+        self.apply_bindings_from(bindings);
+    }
+
+    /// Same as [`Context::apply_bindings()`] for bindings taken apart with [`RawBindings::from()`]
+    pub fn apply_raw_bindings(&mut self, bindings: &RawBindings) {
+        self.apply_bindings_from(bindings);
+    }
+
+    fn apply_bindings_from<B: BindingsSource>(&mut self, bindings: &B) {
+        if self.validation.enabled {
+            if let Err(e) = self.validate_bindings(bindings) {
+                panic!("{}", e);
+            }
+            self.validation.bindings_applied(bindings);
+        }
+
+        let cur_pipeline = self.cache.cur_pipeline.unwrap();
+        let pip = self.pipelines.get(cur_pipeline.0).unwrap_or_else(|| stale_handle(cur_pipeline));
+        let shader = self.shaders.get(pip.shader.0).unwrap_or_else(|| stale_handle(pip.shader));
 
         let gl = &self.glow_ctx.0.gl;
 
         for (n, shader_image) in shader.images.iter().enumerate() {
-            let bindings_image = bindings
-                .images
-                .get(n)
-                .unwrap_or_else(|| panic!("Image count in bindings and shader did not match!"));
+            if n >= bindings.image_count() {
+                panic!("Image count in bindings and shader did not match!");
+            }
             if let Some(gl_loc) = shader_image.gl_loc {
                 unsafe {
-                    self.cache.bind_texture(n, bindings_image.texture);
+                    self.cache.bind_texture(n, bindings.image(n));
                     gl.uniform_1_i32(Some(&gl_loc), n as i32);
                 }
             }
         }
 
-        self.cache.bind_buffer(
-            glow::ELEMENT_ARRAY_BUFFER,
-            Some(bindings.index_buffer.gl_buf),
-            bindings.index_buffer.index_type,
-        );
+        for (n, shader_block) in shader.uniform_blocks.iter().enumerate() {
+            if n >= bindings.uniform_buffer_count() {
+                panic!("Uniform buffer count in bindings and shader did not match!");
+            }
+            if shader_block.index.is_some() {
+                self.cache.bind_uniform_buffer(n, Some(bindings.uniform_buffer(n).0));
+            }
+        }
+
+        match bindings.index_buffer() {
+            Some((index_buffer, _, index_type)) => {
+                self.cache.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer), index_type)
+            }
+            None => self.cache.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None, None),
+        }
 
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
+        let pip = self.pipelines.get(cur_pipeline.0).unwrap_or_else(|| stale_handle(cur_pipeline));
 
         for attr_index in 0..MAX_VERTEX_ATTRIBUTES {
             let cached_attr = self.cache.attributes[attr_index];
@@ -330,7 +650,7 @@ impl Context {
             let pip_attribute = pip.layout.get(attr_index).copied();
 
             if let Some(Some(attribute)) = pip_attribute {
-                let vb = bindings.vertex_buffers[attribute.buffer_index].clone();
+                let (vb, _) = bindings.vertex_buffer(attribute.buffer_index);
 
                 if cached_attr.map_or(true, |cached_attr| {
                     if attribute != cached_attr.attribute {
@@ -338,14 +658,10 @@ impl Context {
                     }
                     match cached_attr.gl_vbuf {
                         None => true,
-                        Some(gl_vbuf) => gl_vbuf != vb.gl_buf
+                        Some(gl_vbuf) => gl_vbuf != vb
                     }
                 }) {
-                    self.cache.bind_buffer(
-                        glow::ARRAY_BUFFER,
-                        Some(vb.gl_buf),
-                        vb.index_type
-                    );
+                    self.cache.bind_buffer(glow::ARRAY_BUFFER, Some(vb), None);
 
                     unsafe {
                         gl.vertex_attrib_pointer_f32(
@@ -363,7 +679,7 @@ impl Context {
                     let cached_attr = &mut self.cache.attributes[attr_index];
                     *cached_attr = Some(CachedAttribute {
                         attribute,
-                        gl_vbuf: Some(vb.gl_buf),
+                        gl_vbuf: Some(vb),
                     });
                 }
             } else {
@@ -375,6 +691,8 @@ impl Context {
                 }
             }
         }
+
+        self.poll_gl_errors("Context::apply_bindings");
     }
 
     pub fn apply_uniforms<U>(&mut self, uniforms: &U) {
@@ -382,8 +700,14 @@ impl Context {
     }
 
     fn apply_uniforms_from_bytes(&mut self, uniform_ptr: *const u8, size: usize) {
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
-        let shader = &self.shaders[pip.shader.0];
+        if self.validation.enabled {
+            if let Err(e) = self.validate_uniforms(size) {
+                panic!("{}", e);
+            }
+        }
+
+        let pip = self.pipeline_internal(self.cache.cur_pipeline.unwrap());
+        let shader = self.shader_internal(pip.shader);
 
         let mut offset = 0;
 
@@ -400,6 +724,7 @@ impl Context {
             unsafe {
                 let data = (uniform_ptr as *const f32).offset(offset as isize);
                 let data_int = (uniform_ptr as *const i32).offset(offset as isize);
+                let data_uint = (uniform_ptr as *const u32).offset(offset as isize);
 
                 if let Some(gl_loc) = uniform.gl_loc {
                     match uniform.uniform_type {
@@ -425,7 +750,37 @@ impl Context {
                             gl.uniform_3_i32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_int, (uniform.array_count * 3) as _));
                         }
                         Int4 => {
-                            gl.uniform_3_i32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_int, (uniform.array_count * 4) as _));
+                            gl.uniform_4_i32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_int, (uniform.array_count * 4) as _));
+                        }
+                        UInt1 => {
+                            gl.uniform_1_u32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_uint, uniform.array_count as _));
+                        }
+                        UInt2 => {
+                            gl.uniform_2_u32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_uint, (uniform.array_count * 2) as _));
+                        }
+                        UInt3 => {
+                            gl.uniform_3_u32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_uint, (uniform.array_count * 3) as _));
+                        }
+                        UInt4 => {
+                            gl.uniform_4_u32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_uint, (uniform.array_count * 4) as _));
+                        }
+                        // GL converts integers to booleans on its own, while samplers only accept glUniform1i
+                        Bool | Sampler => {
+                            gl.uniform_1_i32_slice(Some(&gl_loc), std::slice::from_raw_parts(data_int, uniform.array_count as _));
+                        }
+                        Mat2 => {
+                            gl.uniform_matrix_2_f32_slice(
+                                Some(&gl_loc),
+                                false,
+                                std::slice::from_raw_parts(data, (uniform.array_count * 4) as _)
+                            );
+                        }
+                        Mat3 => {
+                            gl.uniform_matrix_3_f32_slice(
+                                Some(&gl_loc),
+                                false,
+                                std::slice::from_raw_parts(data, (uniform.array_count * 9) as _)
+                            );
                         }
                         Mat4 => {
                             gl.uniform_matrix_4_f32_slice(
@@ -434,11 +789,21 @@ impl Context {
                                 std::slice::from_raw_parts(data, (uniform.array_count * 16) as _)
                             );
                         }
+                        Mat2x3 | Mat2x4 | Mat3x2 | Mat3x4 | Mat4x2 | Mat4x3 => {
+                            // Not exposed by glow, so the entry points are loaded along with it.
+                            // Shaders with these uniforms can't be created without them
+                            if let Some(uniform_matrix) = self.glow_ctx.0.raw.uniform_matrix(uniform.uniform_type) {
+                                let location: u32 = std::mem::transmute_copy(&gl_loc);
+                                uniform_matrix(location as i32, uniform.array_count, 0, data);
+                            }
+                        }
                     }
                 }
             }
             offset += uniform.uniform_type.size() / 4 * uniform.array_count as usize;
         }
+
+        self.poll_gl_errors("Context::apply_uniforms");
     }
 
     pub fn clear(
@@ -461,6 +826,10 @@ impl Context {
             bits |= glow::DEPTH_BUFFER_BIT;
             unsafe {
                 gl.clear_depth_f32(v);
+                // glClear respects depth mask, so the pipeline might have turned clearing off
+                if !self.cache.depth_write {
+                    gl.depth_mask(true);
+                }
             }
         }
 
@@ -476,6 +845,31 @@ impl Context {
                 gl.clear(bits);
             }
         }
+
+        if depth.is_some() && !self.cache.depth_write {
+            unsafe {
+                gl.depth_mask(false);
+            }
+        }
+    }
+
+    /// Clears color attachments of the current pass one by one, `colors[i]` goes to
+    /// attachment `i` and `None` entries are skipped.
+    pub fn clear_attachments(
+        &self,
+        colors: &[Option<(f32, f32, f32, f32)>],
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        for (i, color) in colors.iter().enumerate() {
+            if let Some((r, g, b, a)) = *color {
+                unsafe {
+                    self.glow_ctx.0.gl.clear_buffer_f32_slice(glow::COLOR, i as u32, &[r, g, b, a]);
+                }
+            }
+        }
+
+        self.clear(None, depth, stencil);
     }
 
     pub fn begin_default_pass(&mut self, action: PassAction) {
@@ -485,19 +879,34 @@ impl Context {
     pub fn begin_pass(&mut self, pass: impl Into<Option<RenderPass>>, action: PassAction) {
         let (default_w, default_h) = self.window_size;
         let (h_dpi, v_dpi) = self.dpi;
-        let (framebuffer, w, h) = match pass.into() {
+        let pass = pass.into();
+        self.current_pass = pass;
+
+        if self.pass_debug_group {
+            // Previous pass was not ended
+            self.pop_debug_group();
+        }
+        self.pass_debug_group = self.glow_ctx.0.khr_debug;
+        if self.pass_debug_group {
+            let name = match pass {
+                None => "Default pass".to_string(),
+                Some(pass) => match &self.pass_internal(pass).label {
+                    Some(label) => label.clone(),
+                    None => format!("{:?}", pass),
+                },
+            };
+            self.push_debug_group(&name);
+        }
+        let (framebuffer, w, h) = match pass {
             None => (
                 self.default_framebuffer,
                 (default_w as f32 * h_dpi) as i32,
                 (default_h as f32 * v_dpi) as i32,
             ),
             Some(pass) => {
-                let pass = &self.passes[pass.0];
-                (
-                    pass.gl_fb,
-                    pass.texture.width as i32,
-                    pass.texture.height as i32,
-                )
+                let pass = self.pass_internal(pass);
+                let (w, h) = pass.colors[0].size();
+                (pass.gl_fb, w as i32, h as i32)
             }
         };
 
@@ -517,10 +926,29 @@ impl Context {
             } => {
                 self.clear(color, depth, stencil);
             }
+            PassAction::ClearAttachments {
+                colors,
+                depth,
+                stencil,
+            } => {
+                self.clear_attachments(&colors, depth, stencil);
+            }
         }
+
+        self.poll_gl_errors("Context::begin_pass");
     }
 
     pub fn end_render_pass(&mut self) {
+        if let Some(pass) = self.current_pass.take() {
+            let auto_resolve = self.passes
+                .get(pass.0)
+                .and_then(|pass| pass.resolve.as_ref())
+                .is_some_and(|resolve| resolve.auto);
+            if auto_resolve {
+                pass.resolve(self);
+            }
+        }
+
         unsafe {
             self.glow_ctx.0.gl.bind_framebuffer(
                 glow::FRAMEBUFFER,
@@ -529,22 +957,142 @@ impl Context {
             self.cache.bind_buffer(glow::ARRAY_BUFFER, None, None);
             self.cache.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None, None);
         }
+
+        if self.pass_debug_group {
+            self.pop_debug_group();
+            self.pass_debug_group = false;
+        }
+
+        self.poll_gl_errors("Context::end_render_pass");
+    }
+
+    /// Copies `src_rect` of `src` pass into `dst_rect` of `dst` pass, scaling with `filter`
+    /// if rects differ in size. `None` stands for the default framebuffer.
+    ///
+    /// Rects are `(x, y, width, height)` in pixels, with the origin in the bottom left corner.
+    /// Color is read from the first color attachment of `src` and written into every color
+    /// attachment of `dst`.
+    ///
+    /// May be called in the middle of a pass, whose framebuffer is bound again afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if depth or stencil blit is requested with [`FilterMode::Linear`],
+    /// which GL does not support.
+    pub fn blit(
+        &mut self,
+        src: impl Into<Option<RenderPass>>,
+        src_rect: (i32, i32, i32, i32),
+        dst: impl Into<Option<RenderPass>>,
+        dst_rect: (i32, i32, i32, i32),
+        mask: BlitMask,
+        filter: FilterMode,
+    ) {
+        assert!(
+            filter == FilterMode::Nearest || !(mask.depth || mask.stencil),
+            "Depth and stencil can only be blitted with FilterMode::Nearest"
+        );
+
+        let src_fb = self.pass_framebuffer(src.into());
+        let dst_fb = self.pass_framebuffer(dst.into());
+        let (src_x, src_y, src_w, src_h) = src_rect;
+        let (dst_x, dst_y, dst_w, dst_h) = dst_rect;
+
+        let gl = &self.glow_ctx.0.gl;
+
+        unsafe {
+            // Blits are clipped by scissor rect
+            let scissor_test = gl.is_enabled(glow::SCISSOR_TEST);
+            gl.disable(glow::SCISSOR_TEST);
+
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(src_fb));
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(dst_fb));
+            gl.blit_framebuffer(
+                src_x,
+                src_y,
+                src_x + src_w,
+                src_y + src_h,
+                dst_x,
+                dst_y,
+                dst_x + dst_w,
+                dst_y + dst_h,
+                mask.gl_bits(),
+                filter as u32,
+            );
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.pass_framebuffer(self.current_pass)));
+            if scissor_test {
+                gl.enable(glow::SCISSOR_TEST);
+            }
+        }
+
+        self.poll_gl_errors("Context::blit");
     }
 
+    /// Reads back what was rendered into the default framebuffer so far, e.g. for screenshots.
+    ///
+    /// The image is of the size of the window in physical pixels, i.e. with DPI scale applied.
+    pub fn capture_default_framebuffer(&self) -> Image {
+        let (default_w, default_h) = self.window_size;
+        let (h_dpi, v_dpi) = self.dpi;
+        let w = (default_w as f32 * h_dpi) as u32;
+        let h = (default_h as f32 * v_dpi) as u32;
+
+        let mut bytes = vec![0u8; w as usize * h as usize * 4];
+        let gl = &self.glow_ctx.0.gl;
+
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.default_framebuffer));
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                w as i32,
+                h as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut bytes)
+            );
+            // Might be called in the middle of a pass
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.pass_framebuffer(self.current_pass)));
+        }
+
+        self.poll_gl_errors("Context::capture_default_framebuffer");
+
+        Image::from_gl_rows(w, h, bytes)
+    }
+
+    fn pass_framebuffer(&self, pass: Option<RenderPass>) -> glow::Framebuffer {
+        match pass {
+            None => self.default_framebuffer,
+            Some(pass) => self.pass_internal(pass).gl_fb,
+        }
+    }
+
+    /// Ends the frame, reloading watched shaders whose files changed, see [`Shader::from_sources()`].
     pub fn commit_frame(&mut self) {
         self.cache.clear_buffer_bindings();
         self.cache.clear_texture_bindings();
+        self.reload_changed_shaders();
     }
 
     pub fn draw(&self, base_element: i32, num_elements: i32, num_instances: i32) {
+        if self.validation.enabled {
+            if let Err(e) = self.validate_draw(base_element, num_elements, num_instances) {
+                panic!("{}", e);
+            }
+        }
+
         assert!(
             self.cache.cur_pipeline.is_some(),
             "Drawing without any binded pipeline"
         );
 
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
+        let pip = self.pipeline_internal(self.cache.cur_pipeline.unwrap());
         let primitive_type = pip.params.primitive_type.into();
-        let index_type = self.cache.index_type.expect("Unset index buffer type");
+        let index_type = self.cache.index_type.expect(
+            "Drawing without index buffer, use draw_arrays for non-indexed geometry"
+        );
 
         unsafe {
             self.glow_ctx.0.gl.draw_elements_instanced(
@@ -555,18 +1103,56 @@ impl Context {
                 num_instances,
             );
         }
+
+        self.poll_gl_errors("Context::draw");
+    }
+
+    /// Draws `num_vertices` vertices starting from `first_vertex` of bound vertex buffers,
+    /// without any index buffer involved.
+    pub fn draw_arrays(&self, first_vertex: i32, num_vertices: i32, num_instances: i32) {
+        if self.validation.enabled {
+            if let Err(e) = self.validate_draw_arrays(first_vertex, num_vertices, num_instances) {
+                panic!("{}", e);
+            }
+        }
+
+        assert!(
+            self.cache.cur_pipeline.is_some(),
+            "Drawing without any binded pipeline"
+        );
+
+        let pip = self.pipeline_internal(self.cache.cur_pipeline.unwrap());
+        let primitive_type = pip.params.primitive_type.into();
+
+        unsafe {
+            self.glow_ctx.0.gl.draw_arrays_instanced(
+                primitive_type,
+                first_vertex,
+                num_vertices,
+                num_instances,
+            );
+        }
+
+        self.poll_gl_errors("Context::draw_arrays");
     }
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
-        let shaders = std::mem::take(&mut self.shaders);
+        self.disable_debug_output();
+
+        let shaders = std::mem::replace(&mut self.shaders, SlotMap::new());
 
-        for shader in shaders.iter() {
+        for shader in shaders.values() {
             shader.delete(self);
         }
 
         self.shaders = shaders;
+
+        #[cfg(feature = "headless")]
+        if let Some(target) = self.offscreen_target.take() {
+            target.delete(self);
+        }
     }
 }
 
@@ -574,18 +1160,32 @@ impl Drop for Context {
 pub struct PipelineParams {
     pub cull_face: CullFace,
     pub front_face_order: FrontFaceOrder,
+    /// Depth compare function, `Always` together with disabled `depth_write`
+    /// turns depth test off entirely
     pub depth_test: Comparison,
+    /// Depth write mask, independent of `depth_test`: e.g. transparent objects are usually
+    /// drawn with `LessOrEqual` test and writes disabled
     pub depth_write: bool,
+    /// Polygon offset as `(factor, units)`, used for decals and shadow map biasing.
+    ///
+    /// Applies to filled polygons only: lines and points are never offset, since polygon
+    /// rasterization modes other than fill are not exposed.
     pub depth_write_offset: Option<(f32, f32)>,
+    /// Window depth range as `(near, far)`, default: `(0.0, 1.0)`
+    pub depth_range: (f32, f32),
+    /// Clamp depth instead of clipping against near and far planes, see [`Context::set_depth_clamp()`]
+    pub depth_clamp: bool,
     pub color_blend: Option<BlendState>,
     pub alpha_blend: Option<BlendState>,
     pub stencil_test: Option<StencilState>,
     pub color_write: ColorMask,
     pub primitive_type: PrimitiveType,
+    /// Index value restarting strips and fans in indexed draws, see [`Context::set_primitive_restart()`]
+    pub primitive_restart: Option<u32>,
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Pipeline(usize);
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pipeline(SlotKey);
 
 impl Default for PipelineParams {
     fn default() -> PipelineParams {
@@ -595,11 +1195,14 @@ impl Default for PipelineParams {
             depth_test: Comparison::Always, // no depth test,
             depth_write: false,             // no depth write,
             depth_write_offset: None,
+            depth_range: (0.0, 1.0),
+            depth_clamp: false,
             color_blend: None,
             alpha_blend: None,
             stencil_test: None,
             color_write: (true, true, true, true),
             primitive_type: PrimitiveType::Triangles,
+            primitive_restart: None,
         }
     }
 }
@@ -621,115 +1224,182 @@ impl Pipeline {
         shader: Shader,
         params: PipelineParams,
     ) -> Pipeline {
-        #[derive(Clone, Copy, Default)]
-        struct BufferCacheData {
-            stride: i32,
-            offset: i64,
+        Self::try_with_params(ctx, buffer_layout, attributes, shader, params)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        ctx: &mut Context,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: Shader,
+    ) -> Result<Pipeline, PipelineError> {
+        Self::try_with_params(ctx, buffer_layout, attributes, shader, Default::default())
+    }
+
+    pub fn try_with_params(
+        ctx: &mut Context,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: Shader,
+        params: PipelineParams,
+    ) -> Result<Pipeline, PipelineError> {
+        let program = ctx.shader_internal(shader).program;
+        let layout = vertex_layout(ctx, buffer_layout, attributes, program)?;
+        if let Some(restart_index) = params.primitive_restart {
+            ctx.check_primitive_restart(restart_index)?;
         }
 
-        let mut buffer_cache: Vec<BufferCacheData> =
-            vec![BufferCacheData::default(); buffer_layout.len()];
+        let pipeline = PipelineInternal {
+            layout,
+            buffer_layout: buffer_layout.to_vec(),
+            attributes: attributes.to_vec(),
+            shader,
+            params,
+            label: None,
+        };
 
-        for VertexAttribute {
-            format,
-            buffer_index,
-            ..
-        } in attributes
-        {
-            let layout = buffer_layout.get(*buffer_index).unwrap_or_else(|| panic!());
-            let cache = buffer_cache
-                .get_mut(*buffer_index)
-                .unwrap_or_else(|| panic!());
+        Ok(Pipeline(ctx.pipelines.insert(pipeline)))
+    }
 
-            if layout.stride == 0 {
-                cache.stride += format.byte_len();
-            } else {
-                cache.stride = layout.stride;
-            }
-            // WebGL 1 limitation
-            assert!(cache.stride <= 255);
-        }
+    pub fn set_blend(&self, ctx: &mut Context, color_blend: Option<BlendState>) {
+        let pipeline = ctx.pipelines.get_mut(self.0).unwrap_or_else(|| stale_handle(*self));
+        pipeline.params.color_blend = color_blend;
+    }
 
-        let program = ctx.shaders[shader.0].program;
+    /// Names the pipeline in GPU captures: a debug marker with the name is inserted
+    /// whenever the pipeline gets applied. `None` removes the name.
+    pub fn set_label(&self, ctx: &mut Context, label: Option<&str>) {
+        let pipeline = ctx.pipelines.get_mut(self.0).unwrap_or_else(|| stale_handle(*self));
+        pipeline.label = label.map(str::to_string);
+    }
 
-        let attributes_len = attributes
-            .iter()
-            .map(|layout| match layout.format {
-                VertexFormat::Mat4 => 4,
-                _ => 1,
-            })
-            .sum();
-
-        let mut vertex_layout: Vec<Option<VertexAttributeInternal>> = vec![None; attributes_len];
-
-        for VertexAttribute {
-            name,
-            format,
-            buffer_index,
-        } in attributes
-        {
-            let buffer_data = &mut buffer_cache
-                .get_mut(*buffer_index)
-                .unwrap_or_else(|| panic!());
-            let layout = buffer_layout.get(*buffer_index).unwrap_or_else(|| panic!());
+    /// Reports whether the pipeline is still alive, i.e. was not deleted with [`Pipeline::delete()`].
+    pub fn is_valid(&self, ctx: &Context) -> bool {
+        ctx.pipelines.contains_key(self.0)
+    }
 
-            let attr_loc = unsafe {
-                ctx.glow_ctx.0.gl.get_attrib_location(program, *name)
-            };
-            let divisor = if layout.step_func == VertexStep::PerVertex {
-                0
-            } else {
-                layout.step_rate
-            };
+    /// Delete pipeline. The slot it occupied is reused by the pipelines created afterwards,
+    /// while this handle stays invalid.
+    ///
+    /// Shader the pipeline was created with is not deleted.
+    pub fn delete(&self, ctx: &mut Context) {
+        ctx.pipelines.remove(self.0).unwrap_or_else(|| stale_handle(*self));
+        if ctx.cache.cur_pipeline == Some(*self) {
+            ctx.cache.cur_pipeline = None;
+        }
+    }
+}
 
-            let mut attributes_count: usize = 1;
-            let mut format = *format;
-
-            if format == VertexFormat::Mat4 {
-                format = VertexFormat::Float4;
-                attributes_count = 4;
-            }
-            for i in 0..attributes_count {
-                if let Some(attr_loc) = attr_loc {
-                    let attr_loc = attr_loc as u32 + i as u32;
-
-                    let attr = VertexAttributeInternal {
-                        attr_loc,
-                        size: format.size(),
-                        type_: format.type_(),
-                        offset: buffer_data.offset,
-                        stride: buffer_data.stride,
-                        buffer_index: *buffer_index,
-                        divisor,
-                    };
-                    //println!("{}: {:?}", name, attr);
+/// Places `attributes` at their locations in the linked `program`
+fn vertex_layout(
+    ctx: &Context,
+    buffer_layout: &[BufferLayout],
+    attributes: &[VertexAttribute],
+    program: glow::Program,
+) -> Result<Vec<Option<VertexAttributeInternal>>, PipelineError> {
+    #[derive(Clone, Copy, Default)]
+    struct BufferCacheData {
+        stride: i32,
+        offset: i64,
+    }
 
-                    assert!(
-                        attr_loc < vertex_layout.len() as u32,
-                        "attribute: {} outside of allocated attributes array len: {}",
-                        name,
-                        vertex_layout.len()
-                    );
-                    vertex_layout[attr_loc as usize] = Some(attr);
-                }
-                buffer_data.offset += format.byte_len() as i64
-            }
+    let mut buffer_cache: Vec<BufferCacheData> =
+        vec![BufferCacheData::default(); buffer_layout.len()];
+
+    for VertexAttribute {
+        name,
+        format,
+        buffer_index,
+    } in attributes
+    {
+        let (layout, cache) = buffer_layout
+            .get(*buffer_index)
+            .zip(buffer_cache.get_mut(*buffer_index))
+            .ok_or_else(|| PipelineError::MissingBufferLayout {
+                attribute: name.to_string(),
+                buffer_index: *buffer_index,
+            })?;
+
+        if layout.stride == 0 {
+            cache.stride += format.byte_len();
+        } else {
+            cache.stride = layout.stride;
+        }
+        // WebGL 1 limitation
+        if cache.stride > 255 {
+            return Err(PipelineError::StrideTooLarge {
+                buffer_index: *buffer_index,
+                stride: cache.stride,
+            });
         }
+    }
 
-        let pipeline = PipelineInternal {
-            layout: vertex_layout,
-            shader,
-            params,
+    let attributes_len = attributes
+        .iter()
+        .map(|layout| match layout.format {
+            VertexFormat::Mat4 => 4,
+            _ => 1,
+        })
+        .sum();
+
+    let mut vertex_layout: Vec<Option<VertexAttributeInternal>> = vec![None; attributes_len];
+
+    for VertexAttribute {
+        name,
+        format,
+        buffer_index,
+    } in attributes
+    {
+        // Both are checked in the loop above
+        let buffer_data = &mut buffer_cache[*buffer_index];
+        let layout = &buffer_layout[*buffer_index];
+
+        let attr_loc = unsafe {
+            ctx.glow_ctx.0.gl.get_attrib_location(program, *name)
+        };
+        let divisor = if layout.step_func == VertexStep::PerVertex {
+            0
+        } else {
+            layout.step_rate
         };
 
-        ctx.pipelines.push(pipeline);
-        Pipeline(ctx.pipelines.len() - 1)
-    }
+        let mut attributes_count: usize = 1;
+        let mut format = *format;
 
-    pub fn set_blend(&self, ctx: &mut Context, color_blend: Option<BlendState>) {
-        let pipeline = &mut ctx.pipelines[self.0];
-        pipeline.params.color_blend = color_blend;
+        if format == VertexFormat::Mat4 {
+            format = VertexFormat::Float4;
+            attributes_count = 4;
+        }
+        for i in 0..attributes_count {
+            if let Some(attr_loc) = attr_loc {
+                let attr_loc = attr_loc as u32 + i as u32;
+
+                let attr = VertexAttributeInternal {
+                    attr_loc,
+                    size: format.size(),
+                    type_: format.type_(),
+                    offset: buffer_data.offset,
+                    stride: buffer_data.stride,
+                    byte_len: format.byte_len(),
+                    buffer_index: *buffer_index,
+                    divisor,
+                };
+                //println!("{}: {:?}", name, attr);
+
+                if attr_loc >= vertex_layout.len() as u32 {
+                    return Err(PipelineError::AttributeLocationOutOfRange {
+                        attribute: name.to_string(),
+                        location: attr_loc,
+                    });
+                }
+                vertex_layout[attr_loc as usize] = Some(attr);
+            }
+            buffer_data.offset += format.byte_len() as i64
+        }
     }
+
+    Ok(vertex_layout)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
@@ -739,21 +1409,30 @@ struct VertexAttributeInternal {
     type_: u32,
     offset: i64,
     stride: i32,
+    /// Size of a single attribute value
+    byte_len: i32,
     buffer_index: usize,
     divisor: i32,
 }
 
 struct PipelineInternal {
     layout: Vec<Option<VertexAttributeInternal>>,
+    /// Kept to place attributes again when the shader gets reloaded
+    buffer_layout: Vec<BufferLayout>,
+    attributes: Vec<VertexAttribute>,
     shader: Shader,
     params: PipelineParams,
+    label: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct Bindings {
     pub vertex_buffers: Vec<Buffer>,
-    pub index_buffer: Buffer,
+    /// Optional for geometry drawn with [`Context::draw_arrays()`]
+    pub index_buffer: Option<Buffer>,
     pub images: Vec<Texture>,
+    /// Storage of the uniform blocks listed in [`ShaderMeta::uniform_blocks`], in the same order
+    pub uniform_buffers: Vec<Buffer>,
 }
 
 impl Drop for Bindings {
@@ -761,10 +1440,15 @@ impl Drop for Bindings {
         for buffer in self.vertex_buffers.iter() {
             buffer.delete();
         }
-        self.index_buffer.delete();
+        if let Some(index_buffer) = self.index_buffer.as_ref() {
+            index_buffer.delete();
+        }
         for image in self.images.iter() {
             image.delete();
         }
+        for buffer in self.uniform_buffers.iter() {
+            buffer.delete();
+        }
     }
 }
 
@@ -776,6 +1460,7 @@ struct CachedAttribute {
     gl_vbuf: Option<glow::Buffer>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum PassAction {
     Nothing,
     Clear {
@@ -783,6 +1468,13 @@ pub enum PassAction {
         depth: Option<f32>,
         stencil: Option<i32>,
     },
+    /// Per attachment clear for passes created with [`RenderPass::new_mrt()`],
+    /// `colors[i]` is the clear value of color attachment `i`, `None` or a missing entry keeps it intact
+    ClearAttachments {
+        colors: Vec<Option<(f32, f32, f32, f32)>>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    },
 }
 
 impl PassAction {
@@ -805,13 +1497,22 @@ impl Default for PassAction {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct RenderPass(usize);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPass(SlotKey);
 
 struct RenderPassInternal {
     gl_fb: glow::Framebuffer,
-    texture: Texture,
-    depth_texture: Option<Texture>,
+    colors: Vec<Attachment>,
+    depth_stencil: Option<Attachment>,
+    resolve: Option<ResolveTarget>,
+    label: Option<String>,
+}
+
+/// Single sampled framebuffer an MSAA pass gets resolved into
+struct ResolveTarget {
+    gl_fb: glow::Framebuffer,
+    textures: Vec<Texture>,
+    auto: bool,
 }
 
 impl RenderPass {
@@ -820,60 +1521,352 @@ impl RenderPass {
         color_img: Texture,
         depth_img: impl Into<Option<Texture>>,
     ) -> RenderPass {
-        let pass = unsafe {
-            let depth_img = depth_img.into();
-            let gl = &context.glow_ctx.0.gl;
-            let gl_fb = gl.create_framebuffer().unwrap();
+        Self::new_mrt(context, &[color_img], depth_img)
+    }
+
+    pub fn try_new(
+        context: &mut Context,
+        color_img: Texture,
+        depth_img: impl Into<Option<Texture>>,
+    ) -> Result<RenderPass, GlError> {
+        Self::try_new_mrt(context, &[color_img], depth_img)
+    }
+
+    /// Creates a pass rendering into several color textures at once,
+    /// `color_imgs[i]` is attached to `COLOR_ATTACHMENTi` and receives fragment shader output `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attachments don't form a valid framebuffer, see [`RenderPass::with_attachments()`].
+    pub fn new_mrt(
+        context: &mut Context,
+        color_imgs: &[Texture],
+        depth_img: impl Into<Option<Texture>>,
+    ) -> RenderPass {
+        Self::try_new_mrt(context, color_imgs, depth_img).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new_mrt(
+        context: &mut Context,
+        color_imgs: &[Texture],
+        depth_img: impl Into<Option<Texture>>,
+    ) -> Result<RenderPass, GlError> {
+        let colors: Vec<Attachment> = color_imgs.iter().cloned().map(Attachment::from).collect();
+        let depth_stencil = depth_img.into().map(Attachment::from);
+
+        Self::with_attachments(context, &colors, depth_stencil)
+    }
+
+    /// Creates a pass from any mix of textures and renderbuffers.
+    ///
+    /// `colors[i]` is attached to `COLOR_ATTACHMENTi`, all attachments are expected to be of the
+    /// same size. The depth attachment also serves as the stencil one for
+    /// [`TextureFormat::Depth24Stencil8`].
+    ///
+    /// On success the pass owns the attachments and deletes them in [`RenderPass::delete()`].
+    pub fn with_attachments(
+        context: &mut Context,
+        colors: &[Attachment],
+        depth_stencil: impl Into<Option<Attachment>>,
+    ) -> Result<RenderPass, GlError> {
+        let depth_stencil = depth_stencil.into();
+        let gl_fb = Self::create_framebuffer(context, colors, depth_stencil.as_ref())?;
+
+        let pass = RenderPassInternal {
+            gl_fb,
+            colors: colors.to_vec(),
+            depth_stencil,
+            resolve: None,
+            label: None,
+        };
+
+        Ok(RenderPass(context.passes.insert(pass)))
+    }
+
+    /// Creates an MSAA pass rendering into multisampled renderbuffers of `samples` samples,
+    /// resolved into `color_imgs` once the pass ends.
+    ///
+    /// Depth (and stencil) attachment of `depth_format` is created alongside and is not resolved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attachments don't form a valid framebuffer, see [`RenderPass::with_resolve()`].
+    pub fn new_msaa(
+        context: &mut Context,
+        color_imgs: &[Texture],
+        depth_format: impl Into<Option<TextureFormat>>,
+        samples: u32,
+    ) -> RenderPass {
+        Self::try_new_msaa(context, color_imgs, depth_format, samples).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new_msaa(
+        context: &mut Context,
+        color_imgs: &[Texture],
+        depth_format: impl Into<Option<TextureFormat>>,
+        samples: u32,
+    ) -> Result<RenderPass, GlError> {
+        let (width, height) = color_imgs
+            .first()
+            .map_or((0, 0), |color_img| (color_img.width, color_img.height));
+
+        // Renderbuffers are created here, so they are deleted here if anything goes wrong
+        let mut renderbuffers: Vec<Attachment> = Vec::with_capacity(color_imgs.len() + 1);
+        let formats = color_imgs
+            .iter()
+            .map(|color_img| color_img.format)
+            .chain(depth_format.into());
+        for format in formats {
+            match Renderbuffer::try_new_multisampled(context, width, height, format, samples) {
+                Ok(renderbuffer) => renderbuffers.push(renderbuffer.into()),
+                Err(e) => {
+                    renderbuffers.iter().for_each(Attachment::delete);
+                    return Err(e);
+                }
+            }
+        }
+        let depth_stencil = if renderbuffers.len() > color_imgs.len() {
+            renderbuffers.pop()
+        } else {
+            None
+        };
+
+        Self::with_resolve(context, &renderbuffers, depth_stencil.clone(), color_imgs)
+            .inspect_err(|_| renderbuffers.iter().chain(depth_stencil.iter()).for_each(Attachment::delete))
+    }
+
+    /// Same as [`RenderPass::with_attachments()`], but color attachment `i` is resolved into
+    /// `resolve_imgs[i]`: automatically when the pass ends (see [`RenderPass::set_auto_resolve()`])
+    /// or explicitly with [`RenderPass::resolve()`].
+    ///
+    /// [`RenderPass::texture()`] and [`RenderPass::color_texture()`] return resolve textures of such pass.
+    pub fn with_resolve(
+        context: &mut Context,
+        colors: &[Attachment],
+        depth_stencil: impl Into<Option<Attachment>>,
+        resolve_imgs: &[Texture],
+    ) -> Result<RenderPass, GlError> {
+        if resolve_imgs.len() != colors.len() {
+            return Err(FramebufferError::ResolveTargetCount {
+                expected: colors.len(),
+                found: resolve_imgs.len(),
+            }.into());
+        }
+
+        let depth_stencil = depth_stencil.into();
+        let gl_fb = Self::create_framebuffer(context, colors, depth_stencil.as_ref())?;
+
+        let resolve_colors: Vec<Attachment> = resolve_imgs.iter().cloned().map(Attachment::from).collect();
+        let resolve_fb = Self::create_framebuffer(context, &resolve_colors, None)
+            .inspect_err(|_| unsafe { context.glow_ctx.0.gl.delete_framebuffer(gl_fb) })?;
+
+        let pass = RenderPassInternal {
+            gl_fb,
+            colors: colors.to_vec(),
+            depth_stencil,
+            resolve: Some(ResolveTarget {
+                gl_fb: resolve_fb,
+                textures: resolve_imgs.to_vec(),
+                auto: true,
+            }),
+            label: None,
+        };
+
+        Ok(RenderPass(context.passes.insert(pass)))
+    }
+
+    fn create_framebuffer(
+        context: &mut Context,
+        colors: &[Attachment],
+        depth_stencil: Option<&Attachment>,
+    ) -> Result<glow::Framebuffer, GlError> {
+        if colors.is_empty() || colors.len() > MAX_COLOR_ATTACHMENTS {
+            return Err(FramebufferError::ColorAttachmentCount(colors.len()).into());
+        }
+        let expected = colors[0].size();
+        for (index, color) in colors.iter().enumerate() {
+            if color.format().is_depth() {
+                return Err(FramebufferError::DepthFormatAsColor { index, format: color.format() }.into());
+            }
+            if color.size() != expected {
+                return Err(FramebufferError::SizeMismatch { expected, found: color.size() }.into());
+            }
+        }
+        if let Some(depth_stencil) = depth_stencil {
+            if !depth_stencil.format().is_depth() {
+                return Err(FramebufferError::ColorFormatAsDepth(depth_stencil.format()).into());
+            }
+            if depth_stencil.size() != expected {
+                return Err(FramebufferError::SizeMismatch { expected, found: depth_stencil.size() }.into());
+            }
+        }
+
+        let gl = &context.glow_ctx.0.gl;
+        let (gl_fb, status) = unsafe {
+            let gl_fb = gl.create_framebuffer().map_err(GlError::ObjectCreation)?;
             gl.bind_framebuffer(glow::FRAMEBUFFER, Some(gl_fb));
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::COLOR_ATTACHMENT0,
-                glow::TEXTURE_2D,
-                color_img.texture,
-                0,
-            );
-            if let Some(depth_img) = depth_img.clone() {
-                gl.framebuffer_texture_2d(
-                    glow::FRAMEBUFFER,
-                    glow::DEPTH_ATTACHMENT,
-                    glow::TEXTURE_2D,
-                    depth_img.texture,
-                    0,
-                );
+            let mut draw_buffers = Vec::with_capacity(colors.len());
+            for (i, color) in colors.iter().enumerate() {
+                let attachment_point = glow::COLOR_ATTACHMENT0 + i as u32;
+                color.attach(gl, attachment_point);
+                draw_buffers.push(attachment_point);
+            }
+            // Draw buffers are framebuffer state, so setting them once here is enough
+            gl.draw_buffers(&draw_buffers);
+            if let Some(depth_stencil) = depth_stencil {
+                let attachment_point = if depth_stencil.format().has_stencil() {
+                    glow::DEPTH_STENCIL_ATTACHMENT
+                } else {
+                    glow::DEPTH_ATTACHMENT
+                };
+                depth_stencil.attach(gl, attachment_point);
             }
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
             gl.bind_framebuffer(
                 glow::FRAMEBUFFER,
                 Some(context.default_framebuffer)
             );
 
-            RenderPassInternal {
-                gl_fb,
-                texture: color_img,
-                depth_texture: depth_img,
+            (gl_fb, status)
+        };
+
+        if let Some(e) = FramebufferError::from_status(status) {
+            unsafe {
+                gl.delete_framebuffer(gl_fb);
             }
+            return Err(e.into());
+        }
+
+        Ok(gl_fb)
+    }
+
+    /// Turns automatic resolve at [`Context::end_render_pass()`] on or off, e.g. for a pass
+    /// rendered in several chunks. Has no effect on passes without resolve textures.
+    pub fn set_auto_resolve(&self, ctx: &mut Context, auto_resolve: bool) {
+        let render_pass = ctx.passes.get_mut(self.0).unwrap_or_else(|| stale_handle(*self));
+
+        if let Some(resolve) = &mut render_pass.resolve {
+            resolve.auto = auto_resolve;
+        }
+    }
+
+    /// Resolves multisampled color attachments into the resolve textures of the pass.
+    ///
+    /// Should not be called in the middle of the pass itself.
+    pub fn resolve(&self, ctx: &mut Context) {
+        let render_pass = ctx.pass_internal(*self);
+        let resolve = match &render_pass.resolve {
+            Some(resolve) => resolve,
+            None => return,
         };
 
-        context.passes.push(pass);
+        let gl = &ctx.glow_ctx.0.gl;
+        let (w, h) = render_pass.colors[0].size();
+        let (w, h) = (w as i32, h as i32);
 
-        RenderPass(context.passes.len() - 1)
+        unsafe {
+            // Blits are clipped by scissor rect of the pass being resolved
+            let scissor_test = gl.is_enabled(glow::SCISSOR_TEST);
+            gl.disable(glow::SCISSOR_TEST);
+
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(render_pass.gl_fb));
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(resolve.gl_fb));
+            let mut draw_buffers = [glow::NONE; MAX_COLOR_ATTACHMENTS];
+            for i in 0..resolve.textures.len() {
+                let attachment_point = glow::COLOR_ATTACHMENT0 + i as u32;
+                gl.read_buffer(attachment_point);
+                // Blit writes into every draw buffer, so leave just the one matching the read buffer
+                draw_buffers[i] = attachment_point;
+                gl.draw_buffers(&draw_buffers[..=i]);
+                gl.blit_framebuffer(0, 0, w, h, 0, 0, w, h, glow::COLOR_BUFFER_BIT, glow::NEAREST);
+                draw_buffers[i] = glow::NONE;
+            }
+
+            let all_draw_buffers: Vec<u32> = (0..resolve.textures.len())
+                .map(|i| glow::COLOR_ATTACHMENT0 + i as u32)
+                .collect();
+            gl.draw_buffers(&all_draw_buffers);
+            gl.read_buffer(glow::COLOR_ATTACHMENT0);
+
+            // Might be called in the middle of another pass
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(ctx.pass_framebuffer(ctx.current_pass)));
+            if scissor_test {
+                gl.enable(glow::SCISSOR_TEST);
+            }
+        }
     }
 
+    /// First color attachment of the pass.
     pub fn texture(&self, ctx: &mut Context) -> Texture {
-        let render_pass = &mut ctx.passes[self.0];
+        self.color_texture(ctx, 0)
+    }
+
+    /// Color attachment `index` of the pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pass has no such attachment or it is a [`Renderbuffer`].
+    pub fn color_texture(&self, ctx: &mut Context, index: usize) -> Texture {
+        let render_pass = ctx.pass_internal(*self);
+
+        if let Some(resolve) = &render_pass.resolve {
+            return resolve.textures[index].clone();
+        }
+
+        render_pass.colors[index]
+            .texture()
+            .unwrap_or_else(|| panic!("Color attachment {} of {:?} is a renderbuffer", index, self))
+            .clone()
+    }
 
-        render_pass.texture.clone()
+    /// Number of color attachments of the pass.
+    pub fn color_texture_count(&self, ctx: &mut Context) -> usize {
+        ctx.pass_internal(*self).colors.len()
+    }
+
+    /// Depth attachment of the pass, `None` if there is none or it is a [`Renderbuffer`].
+    pub fn depth_texture(&self, ctx: &mut Context) -> Option<Texture> {
+        ctx.pass_internal(*self)
+            .depth_stencil
+            .as_ref()
+            .and_then(|depth_stencil| depth_stencil.texture().cloned())
+    }
+
+    /// Names the pass framebuffer in GPU captures and the debug group
+    /// [`Context::begin_pass()`] opens for it. `None` removes the name.
+    pub fn set_label(&self, ctx: &mut Context, label: Option<&str>) {
+        let render_pass = ctx.passes.get_mut(self.0).unwrap_or_else(|| stale_handle(*self));
+        render_pass.label = label.map(str::to_string);
+
+        let gl_fb = render_pass.gl_fb;
+        ctx.glow_ctx.object_label(glow::FRAMEBUFFER, gl_fb, label);
+    }
+
+    /// Reports whether the pass is still alive, i.e. was not deleted with [`RenderPass::delete()`].
+    pub fn is_valid(&self, ctx: &Context) -> bool {
+        ctx.passes.contains_key(self.0)
     }
 
     pub fn delete(&self, ctx: &mut Context) {
-        let render_pass = &mut ctx.passes[self.0];
+        let render_pass = ctx.passes.remove(self.0).unwrap_or_else(|| stale_handle(*self));
 
         unsafe {
             ctx.glow_ctx.0.gl.delete_framebuffer(render_pass.gl_fb);
         }
 
-        render_pass.texture.delete();
-        if let Some(depth_texture) = render_pass.depth_texture.clone() {
-            depth_texture.delete();
+        for color in render_pass.colors.iter() {
+            color.delete();
+        }
+        if let Some(depth_stencil) = &render_pass.depth_stencil {
+            depth_stencil.delete();
+        }
+        if let Some(resolve) = &render_pass.resolve {
+            unsafe {
+                ctx.glow_ctx.0.gl.delete_framebuffer(resolve.gl_fb);
+            }
+            for texture in resolve.textures.iter() {
+                texture.delete();
+            }
         }
     }
 }
@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+use glow::HasContext;
+use crate::{Context, PreprocessError, ShaderType};
+
+/// Output of legacy fragment shaders rewritten for GLSL 300 es/330, which have no `gl_FragColor`
+const FRAG_COLOR: &str = "pipelines_frag_color";
+
+/// GLSL dialect a [`ShaderPreprocessor`] produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlslVersion {
+    /// GLSL ES 1.00 of GLES 2 and WebGL 1
+    Glsl100,
+    /// GLSL ES 3.00 of GLES 3 and WebGL 2
+    Glsl300Es,
+    /// GLSL 3.30 of desktop GL 3.3 core profile
+    Glsl330,
+}
+
+impl GlslVersion {
+    /// Dialect the context compiles natively.
+    pub fn for_context(ctx: &Context) -> GlslVersion {
+        let version = ctx.glow_ctx.0.gl.version();
+        match (version.is_embedded, version.major) {
+            (true, 0..=2) => GlslVersion::Glsl100,
+            (true, _) => GlslVersion::Glsl300Es,
+            (false, _) => GlslVersion::Glsl330,
+        }
+    }
+
+    fn directive(self) -> &'static str {
+        match self {
+            GlslVersion::Glsl100 => "#version 100",
+            GlslVersion::Glsl300Es => "#version 300 es",
+            GlslVersion::Glsl330 => "#version 330 core",
+        }
+    }
+
+    /// Parses arguments of a `#version` directive, desktop versions before 1.30 count as 100
+    /// since they use the same `attribute`/`varying` syntax
+    fn parse(arguments: &str) -> Option<GlslVersion> {
+        let mut tokens = arguments.split_whitespace();
+        let number: u32 = tokens.next()?.parse().ok()?;
+        match (number, tokens.next()) {
+            (_, Some("es")) if number >= 300 => Some(GlslVersion::Glsl300Es),
+            (0..=129, _) => Some(GlslVersion::Glsl100),
+            _ => Some(GlslVersion::Glsl330),
+        }
+    }
+
+    /// Has `in`/`out` storage qualifiers and `texture()` instead of `attribute`/`varying` and `texture2D()`
+    fn is_modern(self) -> bool {
+        self != GlslVersion::Glsl100
+    }
+}
+
+/// Prepares GLSL sources for [`crate::Shader::new()`]: expands `#include "file"` from a set of
+/// added files, injects `#define`s and adapts the source to the target [`GlslVersion`].
+///
+/// Sources may be written in either GLSL 100 or GLSL 300 es/330 style, the dialect is taken
+/// from their `#version` directive (100 without one). When it differs from the target,
+/// `attribute`/`varying` and `in`/`out` declarations, `texture2D`/`textureCube`/`texture`
+/// calls and the fragment output (`gl_FragColor` or a single `out vec4`) are rewritten.
+/// For GLSL 100, `texture`/`textureLod` calls become `textureCube`/`textureCubeLod` when their
+/// first argument is a name declared as `samplerCube` before, and `texture2D`/`texture2DLod`
+/// otherwise. Global `layout` and `smooth` qualifiers are dropped for GLSL 100, which has no
+/// equivalent of `flat`, `noperspective` and `centroid` ones, so those fail with
+/// [`PreprocessError::UnsupportedQualifier`]. `#define` bodies are rewritten as well.
+/// Fragment shaders for GLES get a default `precision mediump float`, which a statement
+/// of their own overrides.
+///
+/// `#extension` directives are moved right after `#version`, ahead of everything injected,
+/// so ones inside `#if` blocks are not supported.
+#[derive(Clone, Debug)]
+pub struct ShaderPreprocessor {
+    target: GlslVersion,
+    files: HashMap<String, String>,
+    defines: Vec<(String, String)>,
+}
+
+impl ShaderPreprocessor {
+    pub fn new(target: GlslVersion) -> ShaderPreprocessor {
+        ShaderPreprocessor {
+            target,
+            files: HashMap::new(),
+            defines: vec![],
+        }
+    }
+
+    /// Targets the dialect of `ctx`, see [`GlslVersion::for_context()`].
+    pub fn for_context(ctx: &Context) -> ShaderPreprocessor {
+        Self::new(GlslVersion::for_context(ctx))
+    }
+
+    pub fn target(&self) -> GlslVersion {
+        self.target
+    }
+
+    /// Adds a file for `#include "name"` to expand to, replacing the file previously added with the same name.
+    pub fn add_file(&mut self, name: &str, source: &str) {
+        self.files.insert(name.to_string(), source.to_string());
+    }
+
+    /// Adds `#define name value` to every processed source, `value` may be empty.
+    pub fn define(&mut self, name: &str, value: &str) {
+        self.defines.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn process(&self, shader_type: ShaderType, source: &str) -> Result<String, PreprocessError> {
+        self.process_with_defines(shader_type, source, &[])
+    }
+
+    /// Same as [`ShaderPreprocessor::process()`], with `defines` added after the ones of the preprocessor.
+    pub fn process_with_defines(
+        &self,
+        shader_type: ShaderType,
+        source: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<String, PreprocessError> {
+        let mut body = String::with_capacity(source.len());
+        let mut extensions = String::new();
+        let mut version = None;
+        self.expand(source, &mut vec![], &mut body, &mut extensions, &mut version)?;
+
+        let source_version = version.unwrap_or(GlslVersion::Glsl100);
+        let fragment = matches!(shader_type, ShaderType::Fragment);
+        let rewrite = source_version.is_modern() != self.target.is_modern();
+
+        let mut output = String::with_capacity(body.len() + 256);
+        output.push_str(self.target.directive());
+        output.push('\n');
+        output.push_str(&extensions);
+        let defines = self.defines.iter().map(|(name, value)| (name.as_str(), value.as_str())).chain(defines.iter().copied());
+        for (name, value) in defines {
+            output.push_str(format!("#define {} {}", name, value).trim_end());
+            output.push('\n');
+        }
+        if fragment && self.target != GlslVersion::Glsl330 {
+            output.push_str("precision mediump float;\n");
+        }
+
+        if !rewrite {
+            output.push_str(&body);
+        } else if self.target.is_modern() {
+            let body = Rewriter::new(fragment, true).rewrite(&body)?;
+            if fragment && body.contains(FRAG_COLOR) {
+                output.push_str(&format!("out vec4 {};\n", FRAG_COLOR));
+            }
+            output.push_str(&body);
+        } else {
+            output.push_str(&Rewriter::new(fragment, false).rewrite(&body)?);
+        }
+
+        Ok(output)
+    }
+
+    /// Appends `source` to `body` with includes expanded, `#version` directives removed,
+    /// the first of which is stored in `version`, and `#extension` ones moved to `extensions`
+    fn expand(
+        &self,
+        source: &str,
+        include_stack: &mut Vec<String>,
+        body: &mut String,
+        extensions: &mut String,
+        version: &mut Option<GlslVersion>,
+    ) -> Result<(), PreprocessError> {
+        for line in source.lines() {
+            let directive = line.trim_start().strip_prefix('#').map(str::trim_start);
+
+            if let Some(arguments) = directive.and_then(|directive| directive.strip_prefix("version")) {
+                let parsed = GlslVersion::parse(arguments)
+                    .ok_or_else(|| PreprocessError::InvalidDirective(line.trim().to_string()))?;
+                version.get_or_insert(parsed);
+            } else if directive.is_some_and(|directive| directive.starts_with("extension")) {
+                extensions.push_str(line.trim());
+                extensions.push('\n');
+            } else if let Some(arguments) = directive.and_then(|directive| directive.strip_prefix("include")) {
+                let arguments = arguments.trim();
+                let name = arguments
+                    .strip_prefix('"')
+                    .and_then(|name| name.strip_suffix('"'))
+                    .or_else(|| arguments.strip_prefix('<').and_then(|name| name.strip_suffix('>')))
+                    .ok_or_else(|| PreprocessError::InvalidDirective(line.trim().to_string()))?;
+
+                if include_stack.iter().any(|included| included == name) {
+                    return Err(PreprocessError::IncludeCycle { name: name.to_string() });
+                }
+                let file = self
+                    .files
+                    .get(name)
+                    .ok_or_else(|| PreprocessError::MissingInclude { name: name.to_string() })?;
+
+                include_stack.push(name.to_string());
+                self.expand(file, include_stack, body, extensions, version)?;
+                include_stack.pop();
+            } else {
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Token level rewriting between GLSL 100 and GLSL 300 es/330, leaves comments and
+/// preprocessor directives other than `#define` bodies intact
+struct Rewriter {
+    fragment: bool,
+    to_modern: bool,
+    /// Name of the `out vec4` of a modern fragment shader, replaced with `gl_FragColor`
+    frag_output: Option<String>,
+    /// Names declared as `samplerCube`, which `texture()` calls sample with `textureCube()` in GLSL 100
+    cube_samplers: Vec<String>,
+}
+
+impl Rewriter {
+    fn new(fragment: bool, to_modern: bool) -> Rewriter {
+        Rewriter {
+            fragment,
+            to_modern,
+            frag_output: None,
+            cube_samplers: vec![],
+        }
+    }
+
+    fn rewrite(mut self, source: &str) -> Result<String, PreprocessError> {
+        let mut output = String::with_capacity(source.len());
+        self.rewrite_tokens(source, false, &mut output)?;
+        Ok(output)
+    }
+
+    /// Rewrites `source` into `output`, in `directive` mode it is the body of a `#define`,
+    /// where nothing counts as global and there are no nested directives
+    fn rewrite_tokens(&mut self, source: &str, directive: bool, output: &mut String) -> Result<(), PreprocessError> {
+        let mut braces = 0;
+        let mut parens = 0;
+        let mut line_start = !directive;
+        let mut rest = source;
+
+        while let Some(c) = rest.chars().next() {
+            let len = if line_start && c == '#' {
+                let len = directive_len(rest);
+                self.directive(&rest[..len], output)?;
+                rest = &rest[len..];
+                continue;
+            } else if rest.starts_with("//") {
+                rest.find('\n').unwrap_or(rest.len())
+            } else if rest.starts_with("/*") {
+                rest.find("*/").map_or(rest.len(), |end| end + 2)
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let global = !directive && braces == 0 && parens == 0;
+                let skipped = self.identifier(&rest[..len], &rest[len..], global, output)?;
+                line_start = false;
+                rest = &rest[len + skipped..];
+                continue;
+            } else {
+                match c {
+                    '{' => braces += 1,
+                    '}' => braces -= 1,
+                    '(' => parens += 1,
+                    ')' => parens -= 1,
+                    _ => {}
+                }
+                c.len_utf8()
+            };
+
+            output.push_str(&rest[..len]);
+            line_start = !directive && (c == '\n' || (line_start && c.is_whitespace()));
+            rest = &rest[len..];
+        }
+
+        Ok(())
+    }
+
+    /// Writes a preprocessor directive to `output`, with the body rewritten for `#define`s
+    fn directive(&mut self, line: &str, output: &mut String) -> Result<(), PreprocessError> {
+        let arguments = line[1..].trim_start();
+        let Some(definition) = arguments.strip_prefix("define").filter(|rest| rest.starts_with(char::is_whitespace)) else {
+            output.push_str(line);
+            return Ok(());
+        };
+
+        // `#define NAME` or `#define NAME(params)` stays as it is
+        let name_len = next_identifier(definition).len() + definition.len() - definition.trim_start().len();
+        let head_len = if definition[name_len..].starts_with('(') {
+            name_len + definition[name_len..].find(')').map_or(definition.len() - name_len, |end| end + 1)
+        } else {
+            name_len
+        };
+        let body_start = line.len() - definition.len() + head_len;
+        output.push_str(&line[..body_start]);
+        self.rewrite_tokens(&line[body_start..], true, output)
+    }
+
+    /// Writes the replacement of `identifier` to `output`, returns how many bytes of `rest` it consumed
+    fn identifier(
+        &mut self,
+        identifier: &str,
+        rest: &str,
+        global: bool,
+        output: &mut String,
+    ) -> Result<usize, PreprocessError> {
+        let replacement = match (self.to_modern, identifier) {
+            (true, "attribute") if global => "in",
+            (true, "varying") if global => if self.fragment { "in" } else { "out" },
+            (true, "texture2D" | "textureCube") => "texture",
+            (true, "texture2DProj") => "textureProj",
+            (true, "texture2DLod" | "textureCubeLod") => "textureLod",
+            (true, "gl_FragColor") => FRAG_COLOR,
+
+            (false, "in") if global => if self.fragment { "varying" } else { "attribute" },
+            (false, "out") if global && !self.fragment => "varying",
+            (false, "out") if global => {
+                // `out vec4 color;` declaration goes away, `color` becomes `gl_FragColor`
+                let end = rest.find(';').map_or(rest.len(), |end| end + 1);
+                self.frag_output = rest[..end]
+                    .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .rfind(|token| !token.is_empty())
+                    .map(str::to_string);
+                return Ok(end);
+            }
+            (false, "layout") if global => {
+                let end = rest.find(')').map_or(rest.len(), |end| end + 1);
+                return Ok(end + rest[end..].len() - rest[end..].trim_start().len());
+            }
+            (false, "smooth") if global => {
+                // Default interpolation, the only one GLSL 100 has
+                return Ok(rest.len() - rest.trim_start().len());
+            }
+            (false, "flat" | "noperspective" | "centroid") if global => {
+                return Err(PreprocessError::UnsupportedQualifier(identifier.to_string()));
+            }
+            (false, "samplerCube") => {
+                self.cube_samplers.push(next_identifier(rest).to_string());
+                identifier
+            }
+            (false, "texture" | "textureLod") => {
+                let sampler = rest.trim_start().strip_prefix('(').map_or("", next_identifier);
+                match (identifier, self.cube_samplers.iter().any(|name| name == sampler)) {
+                    ("texture", true) => "textureCube",
+                    ("texture", false) => "texture2D",
+                    (_, true) => "textureCubeLod",
+                    (_, false) => "texture2DLod",
+                }
+            }
+            (false, "textureProj") => "texture2DProj",
+            (false, _) if self.frag_output.as_deref() == Some(identifier) => "gl_FragColor",
+
+            _ => identifier,
+        };
+
+        output.push_str(replacement);
+        Ok(0)
+    }
+}
+
+/// Length of the directive `source` starts with, up to the first line break not escaped with `\\`
+fn directive_len(source: &str) -> usize {
+    let mut start = 0;
+    while let Some(end) = source[start..].find('\n').map(|end| start + end) {
+        if !source[..end].trim_end_matches('\r').ends_with('\\') {
+            return end;
+        }
+        start = end + 1;
+    }
+    source.len()
+}
+
+/// Identifier `source` starts with, after whitespace
+fn next_identifier(source: &str) -> &str {
+    let source = source.trim_start();
+    let len = source
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(source.len());
+    &source[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(target: GlslVersion, shader_type: ShaderType, source: &str) -> Result<String, PreprocessError> {
+        ShaderPreprocessor::new(target).process(shader_type, source)
+    }
+
+    #[test]
+    fn includes() {
+        for target in [GlslVersion::Glsl100, GlslVersion::Glsl330] {
+            let mut preprocessor = ShaderPreprocessor::new(target);
+            preprocessor.add_file("consts.glsl", "#define TWO 2.0");
+            preprocessor.add_file("common.glsl", "#include \"consts.glsl\"\nfloat twice(float x) { return x * TWO; }\n");
+
+            let source = "#include <common.glsl>\nvoid main() { gl_Position = vec4(twice(1.0)); }\n";
+            assert_eq!(
+                preprocessor.process(ShaderType::Vertex, source).unwrap(),
+                format!(
+                    "{}\n#define TWO 2.0\nfloat twice(float x) {{ return x * TWO; }}\nvoid main() {{ gl_Position = vec4(twice(1.0)); }}\n",
+                    target.directive(),
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn include_errors() {
+        let mut preprocessor = ShaderPreprocessor::new(GlslVersion::Glsl330);
+        preprocessor.add_file("a", "#include \"b\"");
+        preprocessor.add_file("b", "#include \"a\"");
+        preprocessor.add_file("self", "float f;\n#include \"self\"");
+
+        assert_eq!(
+            preprocessor.process(ShaderType::Vertex, "#include \"a\""),
+            Err(PreprocessError::IncludeCycle { name: "a".to_string() }),
+        );
+        assert_eq!(
+            preprocessor.process(ShaderType::Vertex, "#include \"self\""),
+            Err(PreprocessError::IncludeCycle { name: "self".to_string() }),
+        );
+        assert_eq!(
+            preprocessor.process(ShaderType::Vertex, "#include \"missing\""),
+            Err(PreprocessError::MissingInclude { name: "missing".to_string() }),
+        );
+        assert_eq!(
+            preprocessor.process(ShaderType::Vertex, "#include missing"),
+            Err(PreprocessError::InvalidDirective("#include missing".to_string())),
+        );
+        // Including the same file twice without a cycle is fine
+        preprocessor.add_file("c", "#include \"d\"\n#include \"d\"");
+        preprocessor.add_file("d", "float d;");
+        assert!(preprocessor.process(ShaderType::Vertex, "#include \"c\"").is_ok());
+    }
+
+    #[test]
+    fn define_order() {
+        let mut preprocessor = ShaderPreprocessor::new(GlslVersion::Glsl100);
+        preprocessor.define("A", "1");
+        preprocessor.define("EMPTY", "");
+        let source = "#version 330\n#extension GL_EXT_a : enable\nout vec4 color;\nvoid main() { color = vec4(A + B + C); }";
+
+        assert_eq!(
+            preprocessor.process_with_defines(ShaderType::Fragment, source, &[("C", "3"), ("B", "2")]).unwrap(),
+            "#version 100\n#extension GL_EXT_a : enable\n#define A 1\n#define EMPTY\n#define C 3\n#define B 2\n\
+             precision mediump float;\n\nvoid main() { gl_FragColor = vec4(A + B + C); }\n",
+        );
+
+        let mut preprocessor = ShaderPreprocessor::new(GlslVersion::Glsl330);
+        preprocessor.define("A", "1");
+        let source = "#extension GL_EXT_a : enable\nvoid main() { gl_FragColor = vec4(A + B); }";
+        assert_eq!(
+            preprocessor.process_with_defines(ShaderType::Fragment, source, &[("B", "2")]).unwrap(),
+            "#version 330 core\n#extension GL_EXT_a : enable\n#define A 1\n#define B 2\n\
+             out vec4 pipelines_frag_color;\nvoid main() { pipelines_frag_color = vec4(A + B); }\n",
+        );
+    }
+
+    #[test]
+    fn extensions_are_hoisted() {
+        let mut preprocessor = ShaderPreprocessor::new(GlslVersion::Glsl100);
+        preprocessor.add_file("ext", "#extension GL_EXT_b : require\nfloat f;");
+        let source = "#version 300 es\nprecision highp float;\n#extension GL_EXT_a : enable\n#include \"ext\"\n\
+                      out vec4 color;\nvoid main() { color = vec4(f); }";
+        assert_eq!(
+            preprocessor.process(ShaderType::Fragment, source).unwrap(),
+            "#version 100\n#extension GL_EXT_a : enable\n#extension GL_EXT_b : require\nprecision mediump float;\n\
+             precision highp float;\nfloat f;\n\nvoid main() { gl_FragColor = vec4(f); }\n",
+        );
+
+        let mut preprocessor = ShaderPreprocessor::new(GlslVersion::Glsl300Es);
+        preprocessor.add_file("ext", "#extension GL_EXT_b : require\nfloat f;");
+        let source = "precision highp float;\n#extension GL_EXT_a : enable\n#include \"ext\"\n\
+                      void main() { gl_FragColor = vec4(f); }";
+        assert_eq!(
+            preprocessor.process(ShaderType::Fragment, source).unwrap(),
+            "#version 300 es\n#extension GL_EXT_a : enable\n#extension GL_EXT_b : require\nprecision mediump float;\n\
+             out vec4 pipelines_frag_color;\nprecision highp float;\nfloat f;\n\
+             void main() { pipelines_frag_color = vec4(f); }\n",
+        );
+    }
+
+    #[test]
+    fn storage_qualifiers() {
+        let source = "attribute vec2 pos;\nvarying vec2 uv;\n\
+                      void f(in vec2 p, out vec2 q, inout vec2 r) { q = p + r; }\n\
+                      void main() { f(pos, uv, uv); }\n";
+        assert_eq!(
+            process(GlslVersion::Glsl330, ShaderType::Vertex, source).unwrap(),
+            "#version 330 core\nin vec2 pos;\nout vec2 uv;\n\
+             void f(in vec2 p, out vec2 q, inout vec2 r) { q = p + r; }\n\
+             void main() { f(pos, uv, uv); }\n",
+        );
+        assert_eq!(
+            process(GlslVersion::Glsl300Es, ShaderType::Fragment, "varying vec2 uv;\nvoid main() {}").unwrap(),
+            "#version 300 es\nprecision mediump float;\nin vec2 uv;\nvoid main() {}\n",
+        );
+
+        let source = "#version 330\nin vec2 pos;\nout vec2 uv;\n\
+                      void f(in vec2 p,\n       out vec2 q) { q = p; }\n\
+                      void main() { f(pos, uv); }\n";
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Vertex, source).unwrap(),
+            "#version 100\nattribute vec2 pos;\nvarying vec2 uv;\n\
+             void f(in vec2 p,\n       out vec2 q) { q = p; }\n\
+             void main() { f(pos, uv); }\n",
+        );
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Fragment, "#version 330\nin vec2 uv;\nvoid main() {}").unwrap(),
+            "#version 100\nprecision mediump float;\nvarying vec2 uv;\nvoid main() {}\n",
+        );
+    }
+
+    #[test]
+    fn interpolation_qualifiers() {
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Vertex, "#version 300 es\nsmooth out vec2 uv;").unwrap(),
+            "#version 100\nvarying vec2 uv;\n",
+        );
+        for qualifier in ["flat", "noperspective", "centroid"] {
+            let source = format!("#version 300 es\n{} out int id;", qualifier);
+            assert_eq!(
+                process(GlslVersion::Glsl100, ShaderType::Vertex, &source),
+                Err(PreprocessError::UnsupportedQualifier(qualifier.to_string())),
+            );
+        }
+        assert_eq!(
+            process(GlslVersion::Glsl330, ShaderType::Vertex, "#version 300 es\nflat out int id;").unwrap(),
+            "#version 330 core\nflat out int id;\n",
+        );
+    }
+
+    #[test]
+    fn layout_qualifiers() {
+        let source = "#version 330\nlayout(location = 0) in vec2 pos;\nvoid main() {}";
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Vertex, source).unwrap(),
+            "#version 100\nattribute vec2 pos;\nvoid main() {}\n",
+        );
+        assert_eq!(
+            process(GlslVersion::Glsl300Es, ShaderType::Vertex, source).unwrap(),
+            "#version 300 es\nlayout(location = 0) in vec2 pos;\nvoid main() {}\n",
+        );
+    }
+
+    #[test]
+    fn cube_samplers() {
+        let source = "#version 330\nuniform samplerCube env;\nuniform sampler2D tex;\nin vec3 dir;\nout vec4 color;\n\
+                      void main() { color = texture(env, dir) + texture(tex, dir.xy) + textureLod(env, dir, 0.0); }";
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Fragment, source).unwrap(),
+            "#version 100\nprecision mediump float;\nuniform samplerCube env;\nuniform sampler2D tex;\nvarying vec3 dir;\n\n\
+             void main() { gl_FragColor = textureCube(env, dir) + texture2D(tex, dir.xy) + textureCubeLod(env, dir, 0.0); }\n",
+        );
+
+        let source = "uniform samplerCube env;\nuniform sampler2D tex;\nvarying vec3 dir;\n\
+                      void main() { gl_FragColor = textureCube(env, dir) + texture2D(tex, dir.xy); }";
+        assert_eq!(
+            process(GlslVersion::Glsl330, ShaderType::Fragment, source).unwrap(),
+            "#version 330 core\nout vec4 pipelines_frag_color;\nuniform samplerCube env;\nuniform sampler2D tex;\nin vec3 dir;\n\
+             void main() { pipelines_frag_color = texture(env, dir) + texture(tex, dir.xy); }\n",
+        );
+    }
+
+    #[test]
+    fn fragment_output() {
+        let source = "#version 300 es\nprecision highp float;\nout vec4 result;\n\
+                      void main() { result = vec4(1.0); result.a = 0.5; }";
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Fragment, source).unwrap(),
+            "#version 100\nprecision mediump float;\nprecision highp float;\n\n\
+             void main() { gl_FragColor = vec4(1.0); gl_FragColor.a = 0.5; }\n",
+        );
+
+        let source = "void main() { gl_FragColor = vec4(1.0); gl_FragColor.a = 0.5; }";
+        assert_eq!(
+            process(GlslVersion::Glsl330, ShaderType::Fragment, source).unwrap(),
+            "#version 330 core\nout vec4 pipelines_frag_color;\n\
+             void main() { pipelines_frag_color = vec4(1.0); pipelines_frag_color.a = 0.5; }\n",
+        );
+    }
+
+    #[test]
+    fn define_bodies() {
+        let source = "#define S(t, c) texture2D(t, c)\n#define COLOR gl_FragColor\n#define LONG(t) \\\n    texture2D(t, vec2(0.0))\n\
+                      #ifdef texture2D\n#endif\nuniform sampler2D tex;\n\
+                      void main() { COLOR = S(tex, vec2(1.0)) + LONG(tex); }";
+        assert_eq!(
+            process(GlslVersion::Glsl330, ShaderType::Fragment, source).unwrap(),
+            "#version 330 core\nout vec4 pipelines_frag_color;\n\
+             #define S(t, c) texture(t, c)\n#define COLOR pipelines_frag_color\n#define LONG(t) \\\n    texture(t, vec2(0.0))\n\
+             #ifdef texture2D\n#endif\nuniform sampler2D tex;\n\
+             void main() { COLOR = S(tex, vec2(1.0)) + LONG(tex); }\n",
+        );
+
+        let source = "#version 330\nuniform samplerCube env;\n#define S(c) texture(env, c)\n#define texture_scale 2.0\n\
+                      out vec4 color;\n#define OUT color\nvoid main() { OUT = S(vec3(texture_scale)); }";
+        assert_eq!(
+            process(GlslVersion::Glsl100, ShaderType::Fragment, source).unwrap(),
+            "#version 100\nprecision mediump float;\nuniform samplerCube env;\n#define S(c) textureCube(env, c)\n\
+             #define texture_scale 2.0\n\n#define OUT gl_FragColor\nvoid main() { OUT = S(vec3(texture_scale)); }\n",
+        );
+    }
+}
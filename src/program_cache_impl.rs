@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+use glow::HasContext;
+use crate::Context;
+
+/// Start of every cache file, followed by the format version
+const MAGIC: &[u8; 4] = b"GLPB";
+const FORMAT_VERSION: u32 = 1;
+
+/// Counters of a [`ProgramBinaryCache`], see [`ProgramBinaryCache::stats()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramBinaryCacheStats {
+    /// Programs loaded from binaries
+    pub hits: usize,
+    /// Programs without a binary in the cache, compiled from sources
+    pub misses: usize,
+    /// Binaries the driver refused to load, usually after a driver update, compiled from sources too
+    pub rejected: usize,
+    /// Binaries written to the directory
+    pub stored: usize,
+}
+
+/// Directory with binaries of linked programs, which lets [`crate::Shader`]s skip compilation
+/// on the following runs, see [`Context::set_program_binary_cache()`].
+///
+/// Binaries are looked up by their vertex and fragment sources together with vendor,
+/// renderer and version strings of the driver, so a driver update or a different GPU
+/// compiles everything again. Files are safe to delete at any moment.
+#[derive(Clone, Debug)]
+pub struct ProgramBinaryCache {
+    directory: PathBuf,
+    /// Vendor, renderer and version of the driver, filled when the cache gets installed
+    driver: String,
+    stats: ProgramBinaryCacheStats,
+}
+
+impl ProgramBinaryCache {
+    /// Cache in `directory`, which is created with the first stored binary.
+    pub fn new(directory: impl Into<PathBuf>) -> ProgramBinaryCache {
+        ProgramBinaryCache {
+            directory: directory.into(),
+            driver: String::new(),
+            stats: ProgramBinaryCacheStats::default(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn stats(&self) -> ProgramBinaryCacheStats {
+        self.stats
+    }
+
+    /// Full key of a program, stored next to the binary to tell hash collisions apart
+    fn key(&self, vertex_shader: &str, fragment_shader: &str) -> Vec<u8> {
+        [self.driver.as_bytes(), vertex_shader.as_bytes(), fragment_shader.as_bytes()].join(&0)
+    }
+
+    fn path(&self, key: &[u8]) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", fnv1a(key)))
+    }
+
+    /// Reads the binary format and data stored for `key`
+    fn read(&self, key: &[u8]) -> Option<(u32, Vec<u8>)> {
+        let file = std::fs::read(self.path(key)).ok()?;
+
+        let (magic, rest) = split(&file, MAGIC.len())?;
+        let (version, rest) = split_u32(rest)?;
+        if magic != MAGIC || version != FORMAT_VERSION {
+            return None;
+        }
+        let (format, rest) = split_u32(rest)?;
+        let (key_len, rest) = split_u32(rest)?;
+        let (file_key, binary) = split(rest, key_len as usize)?;
+        if file_key != key {
+            return None;
+        }
+
+        Some((format, binary.to_vec()))
+    }
+
+    fn write(&self, key: &[u8], format: u32, binary: &[u8]) -> std::io::Result<()> {
+        let mut file = Vec::with_capacity(16 + key.len() + binary.len());
+        file.extend_from_slice(MAGIC);
+        file.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        file.extend_from_slice(&format.to_le_bytes());
+        file.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        file.extend_from_slice(key);
+        file.extend_from_slice(binary);
+
+        std::fs::create_dir_all(&self.directory)?;
+        // Written aside and renamed, so other instances of the application never read half a file
+        let path = self.path(key);
+        let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&temporary, file)?;
+        std::fs::rename(&temporary, &path)
+    }
+}
+
+impl Context {
+    /// Installs `cache` for every shader created afterwards, `None` removes the installed one.
+    ///
+    /// Returns `false` and leaves shaders compiled as usual when the driver can't save program
+    /// binaries (no `ARB_get_program_binary`/GLES 3, or a context made with [`Context::from_glow()`]).
+    pub fn set_program_binary_cache(&mut self, cache: Option<ProgramBinaryCache>) -> bool {
+        let mut cache = match cache {
+            Some(cache) if self.glow_ctx.supports_program_binaries() => cache,
+            Some(_) => {
+                self.program_cache = None;
+                return false;
+            }
+            None => {
+                self.program_cache = None;
+                return true;
+            }
+        };
+
+        let gl = &self.glow_ctx.0.gl;
+        cache.driver = unsafe {
+            [glow::VENDOR, glow::RENDERER, glow::VERSION]
+                .map(|parameter| gl.get_parameter_string(parameter))
+                .join("\n")
+        };
+        self.program_cache = Some(cache);
+        true
+    }
+
+    pub fn program_binary_cache(&self) -> Option<&ProgramBinaryCache> {
+        self.program_cache.as_ref()
+    }
+
+    /// Key of the program in the installed cache, `None` without one
+    pub(crate) fn program_cache_key(&self, vertex_shader: &str, fragment_shader: &str) -> Option<Vec<u8>> {
+        self.program_cache.as_ref().map(|cache| cache.key(vertex_shader, fragment_shader))
+    }
+
+    /// Creates a program from the binary stored for `key`, `None` when there is none or the driver rejects it
+    pub(crate) fn load_cached_program(&mut self, key: &[u8]) -> Option<glow::Program> {
+        let cache = self.program_cache.as_mut()?;
+        let (format, binary) = match cache.read(key) {
+            Some(stored) => stored,
+            None => {
+                cache.stats.misses += 1;
+                return None;
+            }
+        };
+
+        // Errors of earlier calls are reported before `program_binary` drops them
+        self.poll_gl_errors("calls made before loading a cached program");
+
+        let cache = self.program_cache.as_mut()?;
+        let gl = &self.glow_ctx.0.gl;
+        unsafe {
+            // Compiling from sources reports the failure properly
+            let program = gl.create_program().ok()?;
+            if self.glow_ctx.program_binary(program, format, &binary) {
+                cache.stats.hits += 1;
+                Some(program)
+            } else {
+                gl.delete_program(program);
+                cache.stats.rejected += 1;
+                None
+            }
+        }
+    }
+
+    /// Saves the binary of a freshly linked `program`, failures only leave it uncached
+    pub(crate) fn store_cached_program(&mut self, key: &[u8], program: glow::Program) {
+        let cache = match self.program_cache.as_mut() {
+            Some(cache) => cache,
+            None => return,
+        };
+
+        if let Some((format, binary)) = self.glow_ctx.get_program_binary(program) {
+            if cache.write(key, format, &binary).is_ok() {
+                cache.stats.stored += 1;
+            }
+        }
+    }
+}
+
+fn split(bytes: &[u8], at: usize) -> Option<(&[u8], &[u8])> {
+    if bytes.len() >= at {
+        Some(bytes.split_at(at))
+    } else {
+        None
+    }
+}
+
+fn split_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let (value, rest) = split(bytes, 4)?;
+    Some((u32::from_le_bytes(value.try_into().unwrap()), rest))
+}
+
+/// 64 bit FNV-1a, unlike `DefaultHasher` it is guaranteed to stay the same between Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
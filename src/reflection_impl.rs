@@ -0,0 +1,209 @@
+use glow::HasContext;
+use crate::{Context, Shader, ShaderError, ShaderMeta, UniformBlockLayout, UniformDesc, UniformType, VertexFormat};
+use crate::shader_impl::{check_uniform_types, link_program, shader_internal_from_program};
+
+/// Active uniform of a linked program, as reported by `glGetActiveUniform`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedUniform {
+    /// Name without the `[0]` suffix arrays are reported with, struct members keep their full path
+    pub name: String,
+    /// `GL_FLOAT_VEC3`, `GL_SAMPLER_2D` etc.
+    pub gl_type: u32,
+    /// `None` for types which can't be uploaded with `apply_uniforms`, like double precision ones
+    pub uniform_type: Option<UniformType>,
+    pub array_count: usize,
+    /// `None` for members of uniform blocks
+    pub location: Option<i32>,
+}
+
+/// Active vertex attribute of a linked program, as reported by `glGetActiveAttrib`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedAttribute {
+    pub name: String,
+    /// `GL_FLOAT_VEC2`, `GL_FLOAT_MAT4` etc.
+    pub gl_type: u32,
+    /// Float format of the same size, `None` for integer attributes. Buffers may still feed
+    /// float attributes with bytes or shorts, e.g. `Byte4` colors going into a `vec4`
+    pub format: Option<VertexFormat>,
+    pub array_count: usize,
+    pub location: u32,
+}
+
+/// Active uniform block of a linked program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedUniformBlock {
+    pub name: String,
+    /// `GL_UNIFORM_BLOCK_DATA_SIZE`, in bytes
+    pub size: usize,
+}
+
+/// Everything a linked program reads, see [`Shader::reflect()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderReflection {
+    pub uniforms: Vec<ReflectedUniform>,
+    pub attributes: Vec<ReflectedAttribute>,
+    /// Empty for contexts created with [`Context::from_glow()`], which lack `glGetProgramiv`
+    pub uniform_blocks: Vec<ReflectedUniformBlock>,
+}
+
+impl Shader {
+    /// Same as [`Shader::new()`], with `ShaderMeta` built from the active uniforms of the program.
+    ///
+    /// Uniforms go in the order of their active uniform indices. Most drivers number uniforms in
+    /// the order of declaration, but GL doesn't require it, so shaders uploaded with a uniforms
+    /// struct should pass its layout to [`Shader::new_reflected_with_uniforms()`] instead.
+    /// Samplers become images, sampler arrays take one image per element, and uniform blocks
+    /// get bound in the order GL reports them.
+    pub fn new_reflected(
+        ctx: &mut Context,
+        vertex_shader: &str,
+        fragment_shader: &str,
+    ) -> Result<Shader, ShaderError> {
+        Self::new_reflected_impl(ctx, vertex_shader, fragment_shader, None)
+    }
+
+    /// Same as [`Shader::new_reflected()`], with uniforms in the order of `uniforms`, e.g.
+    /// `MyUniforms::uniform_layout()` of a struct with `#[derive(Uniforms)]`.
+    ///
+    /// Types of the uniforms are checked against the active ones, while uniforms optimized
+    /// out by the driver are accepted as is.
+    pub fn new_reflected_with_uniforms(
+        ctx: &mut Context,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        uniforms: UniformBlockLayout,
+    ) -> Result<Shader, ShaderError> {
+        Self::new_reflected_impl(ctx, vertex_shader, fragment_shader, Some(uniforms))
+    }
+
+    fn new_reflected_impl(
+        ctx: &mut Context,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        uniforms: Option<UniformBlockLayout>,
+    ) -> Result<Shader, ShaderError> {
+        let program = link_program(ctx, vertex_shader, fragment_shader)?;
+        let reflection = reflect_program(ctx, program);
+
+        let meta = reflected_meta(&reflection, uniforms)
+            .and_then(|meta| check_uniform_types(ctx, &meta).map(|_| meta));
+        let meta = match meta {
+            Ok(meta) => meta,
+            Err(e) => {
+                unsafe {
+                    ctx.glow_ctx.0.gl.delete_program(program);
+                }
+                return Err(e);
+            }
+        };
+
+        let shader = shader_internal_from_program(ctx, program, &meta);
+        Ok(Shader(ctx.shaders.insert(shader)))
+    }
+
+    /// Lists active uniforms, attributes and uniform blocks of the program, e.g. to compare
+    /// them against a hand written `ShaderMeta`. Inactive ones are optimized out by the driver.
+    pub fn reflect(&self, ctx: &Context) -> ShaderReflection {
+        reflect_program(ctx, ctx.shader_internal(*self).program)
+    }
+}
+
+fn reflect_program(ctx: &Context, program: glow::Program) -> ShaderReflection {
+    let gl = &ctx.glow_ctx.0.gl;
+
+    unsafe {
+        let uniforms = (0..gl.get_active_uniforms(program))
+            .filter_map(|index| gl.get_active_uniform(program, index))
+            .map(|uniform| {
+                let location = gl
+                    .get_uniform_location(program, &uniform.name)
+                    .map(|location| std::mem::transmute_copy::<_, u32>(&location) as i32);
+                ReflectedUniform {
+                    name: strip_array_suffix(&uniform.name).to_string(),
+                    gl_type: uniform.utype,
+                    uniform_type: UniformType::from_gl(uniform.utype),
+                    array_count: uniform.size as usize,
+                    location,
+                }
+            })
+            .collect();
+
+        let attributes = (0..gl.get_active_attributes(program))
+            .filter_map(|index| gl.get_active_attribute(program, index))
+            .filter_map(|attribute| {
+                // Built-ins like gl_VertexID are reported without a location
+                let location = gl.get_attrib_location(program, &attribute.name)?;
+                Some(ReflectedAttribute {
+                    name: strip_array_suffix(&attribute.name).to_string(),
+                    gl_type: attribute.atype,
+                    format: VertexFormat::from_gl(attribute.atype),
+                    array_count: attribute.size as usize,
+                    location,
+                })
+            })
+            .collect();
+
+        let block_count = ctx
+            .glow_ctx
+            .get_program_parameter(program, glow::ACTIVE_UNIFORM_BLOCKS)
+            .unwrap_or(0) as u32;
+        let uniform_blocks = (0..block_count)
+            .map(|index| ReflectedUniformBlock {
+                name: gl.get_active_uniform_block_name(program, index),
+                size: gl.get_active_uniform_block_parameter_i32(program, index, glow::UNIFORM_BLOCK_DATA_SIZE) as usize,
+            })
+            .collect();
+
+        ShaderReflection {
+            uniforms,
+            attributes,
+            uniform_blocks,
+        }
+    }
+}
+
+/// `uniforms` replaces the reflected uniforms when given, after checking it against them
+fn reflected_meta(
+    reflection: &ShaderReflection,
+    uniforms: Option<UniformBlockLayout>,
+) -> Result<ShaderMeta, ShaderError> {
+    let mut meta = ShaderMeta::default();
+    for uniform in reflection.uniforms.iter().filter(|uniform| uniform.location.is_some()) {
+        match uniform.uniform_type {
+            Some(UniformType::Sampler) if uniform.array_count > 1 => {
+                meta.images.extend((0..uniform.array_count).map(|i| format!("{}[{}]", uniform.name, i)));
+            }
+            Some(UniformType::Sampler) => meta.images.push(uniform.name.clone()),
+            Some(uniform_type) => meta.uniforms.uniforms.push(
+                UniformDesc::new(&uniform.name, uniform_type).array(uniform.array_count)
+            ),
+            None => {
+                return Err(ShaderError::UnsupportedUniformType {
+                    name: uniform.name.clone(),
+                    gl_type: uniform.gl_type,
+                })
+            }
+        }
+    }
+    meta.uniform_blocks = reflection.uniform_blocks.iter().map(|block| block.name.clone()).collect();
+
+    if let Some(uniforms) = uniforms {
+        for desc in &uniforms.uniforms {
+            let active = meta.uniforms.uniforms.iter().find(|active| active.name == desc.name);
+            if let Some(active) = active.filter(|active| active.uniform_type != desc.uniform_type) {
+                return Err(ShaderError::UniformTypeMismatch {
+                    name: desc.name.clone(),
+                    expected: desc.uniform_type,
+                    found: active.uniform_type,
+                });
+            }
+        }
+        meta.uniforms = uniforms;
+    }
+
+    Ok(meta)
+}
+
+fn strip_array_suffix(name: &str) -> &str {
+    name.strip_suffix("[0]").unwrap_or(name)
+}
@@ -0,0 +1,238 @@
+use std::error::Error;
+use std::fmt::Display;
+use glow::HasContext;
+use crate::{Context, GlError, GlowContext, Texture, TextureFormat};
+
+/// Write-only render target, cheaper than a [`Texture`] when the result never gets sampled
+/// (e.g. the depth buffer of a pass only its color is used from).
+#[derive(Clone)]
+pub struct Renderbuffer {
+    glow_ctx: GlowContext,
+    pub(crate) renderbuffer: glow::Renderbuffer,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Number of samples per pixel, 0 for a single sampled renderbuffer
+    pub samples: u32,
+}
+
+impl Renderbuffer {
+    pub fn new(ctx: &mut Context, width: u32, height: u32, format: TextureFormat) -> Renderbuffer {
+        Self::new_multisampled(ctx, width, height, format, 0)
+    }
+
+    pub fn try_new(
+        ctx: &mut Context,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<Renderbuffer, GlError> {
+        Self::try_new_multisampled(ctx, width, height, format, 0)
+    }
+
+    /// Creates a multisampled renderbuffer for an MSAA [`crate::RenderPass`].
+    ///
+    /// `samples` is clamped to `GL_MAX_SAMPLES`, actual count is stored in [`Renderbuffer::samples`].
+    pub fn new_multisampled(
+        ctx: &mut Context,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        samples: u32,
+    ) -> Renderbuffer {
+        Self::try_new_multisampled(ctx, width, height, format, samples)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new_multisampled(
+        ctx: &mut Context,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        samples: u32,
+    ) -> Result<Renderbuffer, GlError> {
+        let gl = &ctx.glow_ctx.0.gl;
+
+        unsafe {
+            let renderbuffer = gl.create_renderbuffer().map_err(GlError::ObjectCreation)?;
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            // Errors of earlier calls are reported before being dropped
+            ctx.poll_gl_errors("calls made before Renderbuffer allocation");
+            GlError::clear(gl);
+            let samples = if samples > 1 {
+                let max_samples = gl.get_parameter_i32(glow::MAX_SAMPLES) as u32;
+                let samples = samples.min(max_samples);
+                gl.renderbuffer_storage_multisample(
+                    glow::RENDERBUFFER,
+                    samples as i32,
+                    sized_internal_format(format),
+                    width as i32,
+                    height as i32
+                );
+                samples
+            } else {
+                gl.renderbuffer_storage(
+                    glow::RENDERBUFFER,
+                    sized_internal_format(format),
+                    width as i32,
+                    height as i32
+                );
+                0
+            };
+            let result = GlError::check(gl);
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+            if let Err(e) = result {
+                gl.delete_renderbuffer(renderbuffer);
+                return Err(e);
+            }
+
+            Ok(Renderbuffer {
+                glow_ctx: ctx.glow_ctx.clone(),
+                renderbuffer,
+                width,
+                height,
+                format,
+                samples,
+            })
+        }
+    }
+
+    /// Names the renderbuffer in GPU captures and debug messages, `None` removes the name.
+    pub fn set_label(&self, label: Option<&str>) {
+        self.glow_ctx.object_label(glow::RENDERBUFFER, self.renderbuffer, label);
+    }
+
+    pub fn delete(&self) {
+        unsafe {
+            self.glow_ctx.0.gl.delete_renderbuffer(self.renderbuffer);
+        }
+    }
+}
+
+/// Renderbuffer storage only accepts sized formats, unlike `glTexImage2D`
+fn sized_internal_format(format: TextureFormat) -> u32 {
+    match format {
+        TextureFormat::RGB8 => glow::RGB8,
+        TextureFormat::RGBA8 => glow::RGBA8,
+        TextureFormat::Depth => glow::DEPTH_COMPONENT16,
+        TextureFormat::Alpha => glow::R8,
+        TextureFormat::Depth24Stencil8 => glow::DEPTH24_STENCIL8,
+        TextureFormat::Depth32F => glow::DEPTH_COMPONENT32F,
+    }
+}
+
+/// Image a [`crate::RenderPass`] renders into.
+#[derive(Clone)]
+pub enum Attachment {
+    Texture(Texture),
+    Renderbuffer(Renderbuffer),
+}
+
+impl From<Texture> for Attachment {
+    fn from(texture: Texture) -> Self {
+        Attachment::Texture(texture)
+    }
+}
+
+impl From<Renderbuffer> for Attachment {
+    fn from(renderbuffer: Renderbuffer) -> Self {
+        Attachment::Renderbuffer(renderbuffer)
+    }
+}
+
+impl Attachment {
+    pub fn size(&self) -> (u32, u32) {
+        match self {
+            Attachment::Texture(texture) => (texture.width, texture.height),
+            Attachment::Renderbuffer(renderbuffer) => (renderbuffer.width, renderbuffer.height),
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        match self {
+            Attachment::Texture(texture) => texture.format,
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.format,
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        match self {
+            Attachment::Texture(texture) => Some(texture),
+            Attachment::Renderbuffer(_) => None,
+        }
+    }
+
+    /// Attaches to `attachment_point` of the framebuffer currently bound to `GL_FRAMEBUFFER`
+    pub(crate) unsafe fn attach(&self, gl: &glow::Context, attachment_point: u32) {
+        match self {
+            Attachment::Texture(texture) => gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                attachment_point,
+                glow::TEXTURE_2D,
+                texture.texture,
+                0,
+            ),
+            Attachment::Renderbuffer(renderbuffer) => gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                attachment_point,
+                glow::RENDERBUFFER,
+                Some(renderbuffer.renderbuffer),
+            ),
+        }
+    }
+
+    pub fn delete(&self) {
+        match self {
+            Attachment::Texture(texture) => texture.delete(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.delete(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum FramebufferError {
+    /// A pass needs from 1 to `MAX_COLOR_ATTACHMENTS` color attachments
+    ColorAttachmentCount(usize),
+    /// Color attachment at the given index has a depth format
+    DepthFormatAsColor { index: usize, format: TextureFormat },
+    /// Depth attachment has a color format
+    ColorFormatAsDepth(TextureFormat),
+    /// Attachments are expected to be of the same size as the first color attachment
+    SizeMismatch { expected: (u32, u32), found: (u32, u32) },
+    /// Multisampled pass needs exactly one resolve texture per color attachment
+    ResolveTargetCount { expected: usize, found: usize },
+    /// `GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT`: some attachment is not renderable, e.g. an empty texture
+    IncompleteAttachment,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE`: attachments differ in their number of samples
+    IncompleteMultisample,
+    /// `GL_FRAMEBUFFER_UNSUPPORTED`: the driver can't render into this combination of formats
+    Unsupported,
+    /// Any other status reported by `glCheckFramebufferStatus`
+    Incomplete(u32),
+}
+
+impl FramebufferError {
+    /// Maps a `glCheckFramebufferStatus` result, `None` stands for a complete framebuffer
+    pub(crate) fn from_status(status: u32) -> Option<FramebufferError> {
+        match status {
+            glow::FRAMEBUFFER_COMPLETE => None,
+            glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Some(FramebufferError::IncompleteAttachment),
+            glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => Some(FramebufferError::IncompleteMultisample),
+            glow::FRAMEBUFFER_UNSUPPORTED => Some(FramebufferError::Unsupported),
+            status => Some(FramebufferError::Incomplete(status)),
+        }
+    }
+}
+
+impl Display for FramebufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for FramebufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
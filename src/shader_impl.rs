@@ -1,12 +1,17 @@
 use std::error::Error;
 use std::fmt::Display;
 use glow::HasContext;
-use crate::{Context};
+use crate::{Context, PipelineError, PreprocessError, MAX_SHADERSTAGE_UNIFORM_BLOCKS};
+use crate::hot_reload_impl::ShaderWatch;
 use crate::types_impl::{UniformBlockLayout, UniformType};
+use crate::slot_map::{SlotKey, stale_handle};
 
+#[derive(Clone, Debug, Default)]
 pub struct ShaderMeta {
     pub uniforms: UniformBlockLayout,
     pub images: Vec<String>,
+    /// Names of uniform blocks, block `i` reads from `Bindings::uniform_buffers[i]`
+    pub uniform_blocks: Vec<String>,
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -24,6 +29,22 @@ pub enum ShaderError {
     LinkError(String),
     /// Shader strings should never contains \00 in the middle
     FFINulError(std::ffi::NulError),
+    /// Active uniform `Shader::new_reflected` can't describe with a `UniformType`
+    UnsupportedUniformType { name: String, gl_type: u32 },
+    /// Uniform passed to `Shader::new_reflected_with_uniforms` is of another type in the program
+    UniformTypeMismatch { name: String, expected: UniformType, found: UniformType },
+    /// Uniform of a type the context has no entry point to upload, i.e. a non-square matrix
+    /// with a context created by `Context::from_glow`
+    UniformTypeUnavailable { name: String, uniform_type: UniformType },
+    /// Source file of `ShaderSource::File` can't be read
+    ReadError { path: std::path::PathBuf, error_message: String },
+    /// Reloaded program places attributes outside of the range a pipeline created with the shader has
+    IncompatiblePipeline(PipelineError),
+    PreprocessError(PreprocessError),
+    /// `ShaderLibrary::add` with an id which is already registered
+    DuplicateShaderId(String),
+    /// `ShaderLibrary::get` with an id which was never added
+    UnknownShaderId(String),
 }
 
 impl From<std::ffi::NulError> for ShaderError {
@@ -32,6 +53,12 @@ impl From<std::ffi::NulError> for ShaderError {
     }
 }
 
+impl From<PreprocessError> for ShaderError {
+    fn from(e: PreprocessError) -> ShaderError {
+        ShaderError::PreprocessError(e)
+    }
+}
+
 impl Display for ShaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self) // Display the same way as Debug
@@ -44,8 +71,8 @@ impl Error for ShaderError {
     }
 }
 
-#[derive(Clone, Debug, Copy)]
-pub struct Shader(pub(crate) usize);
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct Shader(pub(crate) SlotKey);
 
 impl Shader {
     pub fn new(
@@ -55,8 +82,30 @@ impl Shader {
         meta: ShaderMeta,
     ) -> Result<Self, ShaderError> {
         let shader = load_shader_internal(ctx, vertex_shader, fragment_shader, meta)?;
-        ctx.shaders.push(shader);
-        Ok(Self(ctx.shaders.len() - 1))
+        Ok(Self(ctx.shaders.insert(shader)))
+    }
+
+    /// Reports whether the shader is still alive, i.e. was not deleted with [`Shader::delete()`].
+    pub fn is_valid(&self, ctx: &Context) -> bool {
+        ctx.shaders.contains_key(self.0)
+    }
+
+    /// Names the shader program in GPU captures and debug messages, `None` removes the name.
+    pub fn set_label(&self, ctx: &mut Context, label: Option<&str>) {
+        let shader = ctx.shaders.get_mut(self.0).unwrap_or_else(|| stale_handle(*self));
+        // Kept for programs rebuilt by hot reload
+        shader.label = label.map(str::to_string);
+        let program = shader.program;
+        ctx.glow_ctx.object_label(glow::PROGRAM, program, label);
+    }
+
+    /// Delete shader program. The slot it occupied is reused by the shaders created afterwards,
+    /// while this handle stays invalid.
+    ///
+    /// Pipelines created with this shader can't be applied anymore and should be deleted too.
+    pub fn delete(&self, ctx: &mut Context) {
+        let shader = ctx.shaders.remove(self.0).unwrap_or_else(|| stale_handle(*self));
+        shader.delete(ctx);
     }
 }
 
@@ -71,10 +120,21 @@ pub struct ShaderUniform {
     pub(crate) array_count: i32,
 }
 
+pub(crate) struct ShaderUniformBlock {
+    /// `None` for blocks optimized out by the driver
+    pub(crate) index: Option<u32>,
+    /// `GL_UNIFORM_BLOCK_DATA_SIZE`, the smallest buffer size the block can be bound to
+    pub(crate) size: usize,
+}
+
 pub(crate) struct ShaderInternal {
     pub(crate) program: glow::Program,
     pub(crate) images: Vec<ShaderImage>,
     pub(crate) uniforms: Vec<ShaderUniform>,
+    pub(crate) uniform_blocks: Vec<ShaderUniformBlock>,
+    /// `Some` for shaders with file sources, see `Shader::from_sources`
+    pub(crate) watch: Option<Box<ShaderWatch>>,
+    pub(crate) label: Option<String>,
 }
 
 impl ShaderInternal {
@@ -92,25 +152,87 @@ fn load_shader_internal(
     fragment_shader: &str,
     meta: ShaderMeta,
 ) -> Result<ShaderInternal, ShaderError> {
+    check_uniform_types(context, &meta)?;
+    let program = link_program(context, vertex_shader, fragment_shader)?;
+    Ok(shader_internal_from_program(context, program, &meta))
+}
+
+/// Links a program from sources, or loads it from the program binary cache when one is installed
+pub(crate) fn link_program(
+    context: &mut Context,
+    vertex_shader: &str,
+    fragment_shader: &str,
+) -> Result<glow::Program, ShaderError> {
+    let cache_key = context.program_cache_key(vertex_shader, fragment_shader);
+    if let Some(program) = cache_key.as_ref().and_then(|key| context.load_cached_program(key)) {
+        return Ok(program);
+    }
+
+    let program = compile_program(context, vertex_shader, fragment_shader, cache_key.is_some())?;
+    if let Some(key) = cache_key {
+        context.store_cached_program(&key, program);
+    }
+
+    Ok(program)
+}
+
+fn compile_program(
+    context: &mut Context,
+    vertex_shader: &str,
+    fragment_shader: &str,
+    binary_retrievable: bool,
+) -> Result<glow::Program, ShaderError> {
     unsafe {
         let vertex_shader = load_shader(context, glow::VERTEX_SHADER, vertex_shader)?;
-        let fragment_shader = load_shader(context,glow::FRAGMENT_SHADER, fragment_shader)?;
+        let fragment_shader = load_shader(context, glow::FRAGMENT_SHADER, fragment_shader)
+            .inspect_err(|_| context.glow_ctx.0.gl.delete_shader(vertex_shader))?;
 
         let gl = &context.glow_ctx.0.gl;
 
         let program = gl.create_program().unwrap();
+        if binary_retrievable {
+            context.glow_ctx.set_program_binary_retrievable(program);
+        }
         gl.attach_shader(program, vertex_shader);
         gl.attach_shader(program, fragment_shader);
         gl.link_program(program);
 
+        gl.delete_shader(vertex_shader);
+        gl.delete_shader(fragment_shader);
+
         if !gl.get_program_link_status(program) {
             let error_message = gl.get_program_info_log(program);
-
-            gl.delete_shader(vertex_shader);
-            gl.delete_shader(fragment_shader);
+            gl.delete_program(program);
             return Err(ShaderError::LinkError(error_message));
         }
 
+        Ok(program)
+    }
+}
+
+/// Checks that every uniform `meta` declares can be uploaded by the context
+pub(crate) fn check_uniform_types(context: &Context, meta: &ShaderMeta) -> Result<(), ShaderError> {
+    let raw = &context.glow_ctx.0.raw;
+    for uniform in &meta.uniforms.uniforms {
+        if uniform.uniform_type.is_non_square_matrix() && raw.uniform_matrix(uniform.uniform_type).is_none() {
+            return Err(ShaderError::UniformTypeUnavailable {
+                name: uniform.name.clone(),
+                uniform_type: uniform.uniform_type,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Looks up locations of everything `meta` declares in a linked `program`
+pub(crate) fn shader_internal_from_program(
+    context: &mut Context,
+    program: glow::Program,
+    meta: &ShaderMeta,
+) -> ShaderInternal {
+    unsafe {
+        let gl = &context.glow_ctx.0.gl;
+
         gl.use_program(Some(program));
 
         let images = meta.images.iter().map(|name| ShaderImage {
@@ -125,14 +247,31 @@ fn load_shader_internal(
             }
         }).collect();
 
-        gl.delete_shader(vertex_shader);
-        gl.delete_shader(fragment_shader);
+        assert!(
+            meta.uniform_blocks.len() <= MAX_SHADERSTAGE_UNIFORM_BLOCKS,
+            "Too many uniform blocks, at most {} are supported",
+            MAX_SHADERSTAGE_UNIFORM_BLOCKS
+        );
+        let uniform_blocks = meta.uniform_blocks.iter().enumerate().map(|(n, name)| {
+            let index = gl.get_uniform_block_index(program, name);
+            let size = match index {
+                Some(index) => {
+                    gl.uniform_block_binding(program, index, n as u32);
+                    gl.get_active_uniform_block_parameter_i32(program, index, glow::UNIFORM_BLOCK_DATA_SIZE) as usize
+                }
+                None => 0,
+            };
+            ShaderUniformBlock { index, size }
+        }).collect();
 
-        Ok(ShaderInternal {
+        ShaderInternal {
             program,
             images,
             uniforms,
-        })
+            uniform_blocks,
+            watch: None,
+            label: None,
+        }
     }
 }
 
@@ -145,6 +284,7 @@ fn load_shader(context: &mut Context, shader_type: u32, source: &str) -> Result<
 
         if !gl.get_shader_compile_status(shader) {
             let error_message = gl.get_shader_info_log(shader);
+            gl.delete_shader(shader);
 
             return Err(ShaderError::CompilationError {
                 shader_type: match shader_type {
@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::{Context, Shader, ShaderError, ShaderMeta, ShaderPreprocessor, ShaderType};
+
+/// Sources a [`ShaderLibrary`] builds the variants of one id from.
+struct ShaderTemplate {
+    vertex: String,
+    fragment: String,
+    meta: ShaderMeta,
+}
+
+/// Counters of a [`ShaderLibrary`], see [`ShaderLibrary::stats()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShaderLibraryStats {
+    /// Distinct (id, defines) combinations built so far
+    pub variants: usize,
+    /// Programs behind the variants, fewer than `variants` when some defines don't change the code
+    pub programs: usize,
+    /// Requests answered with an already built variant
+    pub hits: usize,
+    /// Requests which had to preprocess the sources
+    pub misses: usize,
+}
+
+/// Compiles variants of shaders registered by id on demand, each variant being a set of `#define`s.
+///
+/// Repeat requests for the same id and defines return the same [`Shader`], regardless of the order
+/// the defines are passed in. Variants which preprocess to identical code, e.g. differing only
+/// in defines the sources don't use, share one program.
+/// Shaders are owned by the library and deleted by [`ShaderLibrary::clear()`].
+pub struct ShaderLibrary {
+    preprocessor: ShaderPreprocessor,
+    templates: HashMap<String, ShaderTemplate>,
+    /// (id, defines sorted by name) to the variant
+    variants: HashMap<(String, Vec<(String, String)>), Shader>,
+    /// (id, preprocessed vertex and fragment code) to the program, to share it between variants
+    programs: HashMap<(String, String, String), Shader>,
+    stats: ShaderLibraryStats,
+}
+
+impl ShaderLibrary {
+    /// Variants are built with `preprocessor`, including its files and defines.
+    pub fn new(preprocessor: ShaderPreprocessor) -> ShaderLibrary {
+        ShaderLibrary {
+            preprocessor,
+            templates: HashMap::new(),
+            variants: HashMap::new(),
+            programs: HashMap::new(),
+            stats: ShaderLibraryStats::default(),
+        }
+    }
+
+    pub fn preprocessor(&self) -> &ShaderPreprocessor {
+        &self.preprocessor
+    }
+
+    /// Registers sources to build the variants of `id` from. Nothing is compiled until
+    /// the first [`ShaderLibrary::get()`].
+    ///
+    /// Fails with [`ShaderError::DuplicateShaderId`] when `id` is already registered.
+    pub fn add(
+        &mut self,
+        id: &str,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        meta: ShaderMeta,
+    ) -> Result<(), ShaderError> {
+        if self.templates.contains_key(id) {
+            return Err(ShaderError::DuplicateShaderId(id.to_string()));
+        }
+
+        self.templates.insert(
+            id.to_string(),
+            ShaderTemplate {
+                vertex: vertex_shader.to_string(),
+                fragment: fragment_shader.to_string(),
+                meta,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.templates.contains_key(id)
+    }
+
+    /// Returns the variant of `id` with `defines`, compiling it on the first request.
+    ///
+    /// When a name is defined more than once, the last value wins. Failed variants are not
+    /// remembered, so the next request compiles them again. Ids which were not added
+    /// fail with [`ShaderError::UnknownShaderId`].
+    pub fn get(&mut self, ctx: &mut Context, id: &str, defines: &[(&str, &str)]) -> Result<Shader, ShaderError> {
+        let defines: BTreeMap<&str, &str> = defines.iter().copied().collect();
+        let key = (
+            id.to_string(),
+            defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+        );
+
+        if let Some(&shader) = self.variants.get(&key) {
+            self.stats.hits += 1;
+            return Ok(shader);
+        }
+
+        let template = self
+            .templates
+            .get(id)
+            .ok_or_else(|| ShaderError::UnknownShaderId(id.to_string()))?;
+        self.stats.misses += 1;
+        // Defines the code never mentions can't change it, leaving them out lets such variants share a program
+        let vertex = self.preprocessor.process(ShaderType::Vertex, &template.vertex)?;
+        let fragment = self.preprocessor.process(ShaderType::Fragment, &template.fragment)?;
+        let defines = used_defines(&[&vertex, &fragment], &defines);
+        let vertex = self.preprocessor.process_with_defines(ShaderType::Vertex, &template.vertex, &defines)?;
+        let fragment = self.preprocessor.process_with_defines(ShaderType::Fragment, &template.fragment, &defines)?;
+
+        let program_key = (id.to_string(), vertex, fragment);
+        let shader = match self.programs.get(&program_key) {
+            Some(&shader) => shader,
+            None => {
+                let shader = Shader::new(ctx, &program_key.1, &program_key.2, template.meta.clone())?;
+                self.programs.insert(program_key, shader);
+                self.stats.programs += 1;
+                shader
+            }
+        };
+
+        self.variants.insert(key, shader);
+        self.stats.variants += 1;
+        Ok(shader)
+    }
+
+    pub fn stats(&self) -> ShaderLibraryStats {
+        self.stats
+    }
+
+    /// Deletes every compiled shader, keeping the registered sources. Handles returned
+    /// by [`ShaderLibrary::get()`] become invalid and counters start from zero.
+    pub fn clear(&mut self, ctx: &mut Context) {
+        for shader in self.programs.values() {
+            shader.delete(ctx);
+        }
+        self.programs.clear();
+        self.variants.clear();
+        self.stats = ShaderLibraryStats::default();
+    }
+}
+
+/// Defines mentioned by `sources` or by the values of other used defines, sorted by name
+fn used_defines<'a>(sources: &[&str], defines: &BTreeMap<&'a str, &'a str>) -> Vec<(&'a str, &'a str)> {
+    let mut used: BTreeSet<&str> = BTreeSet::new();
+    let mut pending: Vec<&str> = sources.to_vec();
+    while let Some(source) = pending.pop() {
+        for (&name, &value) in defines {
+            if !used.contains(name) && mentions(source, name) {
+                used.insert(name);
+                pending.push(value);
+            }
+        }
+    }
+
+    defines.iter().map(|(&name, &value)| (name, value)).filter(|(name, _)| used.contains(name)).collect()
+}
+
+fn mentions(source: &str, name: &str) -> bool {
+    source
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|token| token == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use super::used_defines;
+
+    #[test]
+    fn defines_used_through_other_defines() {
+        let defines: BTreeMap<&str, &str> =
+            [("LIGHTS", "MAX_LIGHTS"), ("MAX_LIGHTS", "4"), ("UNUSED", "1")].into_iter().collect();
+
+        assert_eq!(
+            used_defines(&["uniform vec4 lights[LIGHTS];"], &defines),
+            [("LIGHTS", "MAX_LIGHTS"), ("MAX_LIGHTS", "4")],
+        );
+        assert_eq!(used_defines(&["uniform vec4 lights[MAX_LIGHTS];"], &defines), [("MAX_LIGHTS", "4")]);
+        assert_eq!(used_defines(&["void main() {}", "MAX_LIGHTS_2"], &defines), []);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn define_order_does_not_matter() {
+        use crate::*;
+
+        let mut ctx = match Context::new_headless(4, 4) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                eprintln!("skipping, no headless context: {}", e);
+                return;
+            }
+        };
+
+        let vertex = "#version 330\nvoid main() { gl_Position = vec4(A, B, 0, 1); }";
+        let fragment = "#version 330\nout vec4 color;\nvoid main() { color = vec4(1); }";
+        let meta = ShaderMeta { images: vec![], uniforms: UniformBlockLayout { uniforms: vec![] }, uniform_blocks: vec![] };
+        let mut library = ShaderLibrary::new(ShaderPreprocessor::for_context(&ctx));
+        library.add("quad", vertex, fragment, meta).unwrap();
+
+        let first = library.get(&mut ctx, "quad", &[("A", "0.0"), ("B", "1.0"), ("C", "2.0")]).unwrap();
+        let second = library.get(&mut ctx, "quad", &[("B", "1.0"), ("C", "2.0"), ("A", "0.0")]).unwrap();
+        // Last value wins, and C isn't used, so this is yet another variant with the same program
+        let third = library.get(&mut ctx, "quad", &[("A", "1.0"), ("B", "1.0"), ("A", "0.0")]).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, third);
+        assert_eq!(
+            library.stats(),
+            ShaderLibraryStats { variants: 2, programs: 1, hits: 1, misses: 2 },
+        );
+    }
+}
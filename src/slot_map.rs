@@ -0,0 +1,138 @@
+/// Generational index into a [`SlotMap`].
+///
+/// A key stays bound to the generation of the slot it was created for, so once the value is
+/// removed the key never resolves again, even after the slot is reused by another value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Vec-backed storage with free-list slot reuse, used for context owned objects
+/// like shaders, pipelines and render passes.
+pub(crate) struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    free_list: Vec<u32>,
+}
+
+impl<T> SlotMap<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_list: Vec::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> SlotKey {
+        match self.free_list.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                SlotKey { index, generation: slot.generation }
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                self.slots.push(Slot { generation: 0, value: Some(value) });
+                SlotKey { index, generation: 0 }
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_list.push(key.index);
+        Some(value)
+    }
+
+    pub(crate) fn get(&self, key: SlotKey) -> Option<&T> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub(crate) fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        self.slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub(crate) fn contains_key(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let key = SlotKey { index: index as u32, generation: slot.generation };
+            slot.value.as_ref().map(|value| (key, value))
+        })
+    }
+}
+
+/// Reports use of a handle whose object was already deleted.
+#[cold]
+#[track_caller]
+pub(crate) fn stale_handle(handle: impl std::fmt::Debug) -> ! {
+    panic!("{:?} is used after being deleted", handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_bumps_generation() {
+        let mut map = SlotMap::new();
+        let first = map.insert("first");
+        assert_eq!(map.remove(first), Some("first"));
+        assert_eq!(map.remove(first), None);
+
+        let second = map.insert("second");
+        assert_eq!(second.index, first.index);
+        assert_eq!(second.generation, first.generation + 1);
+    }
+
+    #[test]
+    fn stale_keys_are_rejected() {
+        let mut map = SlotMap::new();
+        let stale = map.insert(1);
+        map.remove(stale);
+        let live = map.insert(2);
+
+        assert_eq!(map.get(stale), None);
+        assert_eq!(map.get_mut(stale), None);
+        assert!(!map.contains_key(stale));
+        assert_eq!(map.remove(stale), None);
+        assert_eq!(map.get(live), Some(&2));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(live, &2)]);
+    }
+
+    #[test]
+    fn free_slots_are_reused() {
+        let mut map = SlotMap::new();
+        let keys: Vec<SlotKey> = (0..3).map(|value| map.insert(value)).collect();
+        map.remove(keys[0]);
+        map.remove(keys[2]);
+
+        // Most recently freed slot first, then new slots once the free list is empty
+        assert_eq!(map.insert(10).index, keys[2].index);
+        assert_eq!(map.insert(11).index, keys[0].index);
+        assert_eq!(map.insert(12).index, 3);
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [11, 1, 10, 12]);
+    }
+}
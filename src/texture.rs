@@ -1,6 +1,6 @@
 use std::num::NonZeroU32;
 use glow::{HasContext, PixelPackData, PixelUnpackData};
-use crate::{Context, GlowContext};
+use crate::{Context, GlError, GlowContext};
 
 #[derive(Clone)]
 pub struct Texture {
@@ -47,6 +47,13 @@ impl Texture {
         }
     }
 
+    /// Names the texture in GPU captures and debug messages, `None` removes the name.
+    pub fn set_label(&self, label: Option<&str>) {
+        if let Some(texture) = self.texture {
+            self.glow_ctx.object_label(glow::TEXTURE, texture, label);
+        }
+    }
+
     pub fn delete(&self) {
         unsafe {
             match self.texture {
@@ -74,8 +81,13 @@ pub enum TextureKind {
 pub enum TextureFormat {
     RGB8,
     RGBA8,
+    /// 16-bit depth
     Depth,
     Alpha,
+    /// 24-bit depth packed with 8-bit stencil, attaches to both depth and stencil of a [`crate::RenderPass`]
+    Depth24Stencil8,
+    /// 32-bit floating point depth
+    Depth32F,
 }
 
 /// Converts from TextureFormat to (internal_format, format, pixel_type)
@@ -86,6 +98,8 @@ impl From<TextureFormat> for (u32, u32, u32) {
             TextureFormat::RGBA8 => (glow::RGBA, glow::RGBA, glow::UNSIGNED_BYTE),
             TextureFormat::Depth => (glow::DEPTH_COMPONENT, glow::DEPTH_COMPONENT, glow::UNSIGNED_SHORT),
             TextureFormat::Alpha => (glow::R8, glow::RED, glow::UNSIGNED_BYTE), // texture updates will swizzle Red -> Alpha
+            TextureFormat::Depth24Stencil8 => (glow::DEPTH24_STENCIL8, glow::DEPTH_STENCIL, glow::UNSIGNED_INT_24_8),
+            TextureFormat::Depth32F => (glow::DEPTH_COMPONENT32F, glow::DEPTH_COMPONENT, glow::FLOAT),
         }
     }
 }
@@ -99,6 +113,8 @@ impl TextureFormat {
             TextureFormat::RGBA8 => 4 * square,
             TextureFormat::Depth => 2 * square,
             TextureFormat::Alpha => 1 * square,
+            TextureFormat::Depth24Stencil8 => 4 * square,
+            TextureFormat::Depth32F => 4 * square,
         }
     }
 
@@ -109,8 +125,19 @@ impl TextureFormat {
             TextureFormat::RGBA8 => 4 * cube,
             TextureFormat::Depth => 2 * cube,
             TextureFormat::Alpha => 1 * cube,
+            TextureFormat::Depth24Stencil8 => 4 * cube,
+            TextureFormat::Depth32F => 4 * cube,
         }
     }
+
+    /// Whether the format is meant for the depth attachment of a render pass rather than a color one.
+    pub fn is_depth(self) -> bool {
+        matches!(self, TextureFormat::Depth | TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        self == TextureFormat::Depth24Stencil8
+    }
 }
 
 impl Default for TextureParams {
@@ -167,31 +194,48 @@ impl Texture {
         Self::new(ctx, TextureAccess::RenderTarget, None, params, TextureKind::Texture2D)
     }
 
+    pub fn try_new_render_texture(ctx: &mut Context, params: TextureParams) -> Result<Texture, GlError> {
+        Self::try_new(ctx, TextureAccess::RenderTarget, None, params, TextureKind::Texture2D)
+    }
+
     pub fn new(
         ctx: &mut Context,
-        _access: TextureAccess,
+        access: TextureAccess,
         bytes: Option<&[u8]>,
         params: TextureParams,
         kind: TextureKind,
     ) -> Texture {
+        Self::try_new(ctx, access, bytes, params, kind).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        ctx: &mut Context,
+        _access: TextureAccess,
+        bytes: Option<&[u8]>,
+        params: TextureParams,
+        kind: TextureKind,
+    ) -> Result<Texture, GlError> {
         if let Some(bytes_data) = bytes {
-            assert_eq!(
-                params.format.size(params.width, params.height) as usize,
-                bytes_data.len()
-            );
+            let expected = params.format.size(params.width, params.height) as usize;
+            if expected != bytes_data.len() {
+                return Err(GlError::InvalidDataSize { expected, actual: bytes_data.len() });
+            }
         }
 
         let (internal_format, format, pixel_type) = params.format.into();
 
-        ctx.cache.store_texture_binding(0);
-
         let gl = &ctx.glow_ctx.0.gl;
 
-        let texture;
-        unsafe {
-            texture = gl.create_texture().unwrap();
+        let texture = unsafe { gl.create_texture() }.map_err(GlError::ObjectCreation)?;
+
+        ctx.cache.store_texture_binding(0);
 
+        let result;
+        unsafe {
             ctx.cache.bind_texture(0, Some(texture));
+            // Errors of earlier calls are reported before being dropped
+            ctx.poll_gl_errors("calls made before Texture::try_new");
+            GlError::clear(gl);
             gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
 
             if params.format == TextureFormat::Alpha {
@@ -325,10 +369,18 @@ impl Texture {
                     );
                 }
             }
+            result = GlError::check(gl);
         }
         ctx.cache.restore_texture_binding(0);
 
-        Texture {
+        if let Err(e) = result {
+            unsafe {
+                ctx.glow_ctx.0.gl.delete_texture(texture);
+            }
+            return Err(e);
+        }
+
+        Ok(Texture {
             glow_ctx: ctx.glow_ctx.clone(),
             texture: Some(texture),
             width: params.width,
@@ -336,7 +388,7 @@ impl Texture {
             depth: params.depth,
             format: params.format,
             kind
-        }
+        })
     }
 
     /// Upload texture to GPU with given TextureParams
@@ -384,10 +436,13 @@ impl Texture {
             );
         }
         ctx.cache.restore_texture_binding(0);
+
+        ctx.poll_gl_errors("Texture::set_filter");
     }
 
     pub fn resize(&mut self, ctx: &mut Context, width: u32, height: u32, bytes: Option<&[u8]>) {
         ctx.cache.store_texture_binding(0);
+        ctx.cache.bind_texture(0, self.texture);
 
         let (internal_format, format, pixel_type) = self.format.into();
 
@@ -409,14 +464,18 @@ impl Texture {
         }
 
         ctx.cache.restore_texture_binding(0);
+
+        ctx.poll_gl_errors("Texture::resize");
     }
 
     /// Update whole texture content
     /// bytes should be width * height * 4 size - non rgba8 textures are not supported yet anyway
     pub fn update(&self, ctx: &mut Context, bytes: &[u8]) {
-        assert_eq!(self.size(self.width, self.height, self.depth), bytes.len());
+        self.try_update(ctx, bytes).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        self.update_texture_part(
+    pub fn try_update(&self, ctx: &mut Context, bytes: &[u8]) -> Result<(), GlError> {
+        self.try_update_texture_part(
             ctx,
             0 as _,
             0 as _,
@@ -439,9 +498,29 @@ impl Texture {
         depth: i32,
         bytes: &[u8],
     ) {
-        assert_eq!(self.size(width as _, height as _, depth as _), bytes.len());
-        assert!(x_offset + width <= self.width as _);
-        assert!(y_offset + height <= self.height as _);
+        self.try_update_texture_part(ctx, x_offset, y_offset, z_offset, width, height, depth, bytes)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_update_texture_part(
+        &self,
+        ctx: &mut Context,
+        x_offset: i32,
+        y_offset: i32,
+        z_offset: i32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        bytes: &[u8],
+    ) -> Result<(), GlError> {
+        let expected = self.size(width as _, height as _, depth as _);
+        if expected != bytes.len() {
+            return Err(GlError::InvalidDataSize { expected, actual: bytes.len() });
+        }
+        if x_offset + width > self.width as _ || y_offset + height > self.height as _ {
+            return Err(GlError::RegionOutOfBounds { x_offset, y_offset, width, height });
+        }
 
         ctx.cache.store_texture_binding(0);
         ctx.cache.bind_texture(0, self.texture);
@@ -552,6 +631,9 @@ impl Texture {
         }
 
         ctx.cache.restore_texture_binding(0);
+
+        ctx.poll_gl_errors("Texture::update_texture_part");
+        Ok(())
     }
 
     /// Read texture data into CPU memory
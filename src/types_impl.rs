@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug)]
+use crate::Std140Error;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum UniformType {
     /// One 32-bit wide float (equivalent to `f32`)
     Float1,
@@ -8,16 +10,45 @@ pub enum UniformType {
     Float3,
     /// Four 32-bit wide floats (equivalent to `[f32; 4]`)
     Float4,
-    /// One unsigned 32-bit integers (equivalent to `[u32; 1]`)
+    /// One signed 32-bit integer (equivalent to `i32`)
     Int1,
-    /// Two unsigned 32-bit integers (equivalent to `[u32; 2]`)
+    /// Two signed 32-bit integers (equivalent to `[i32; 2]`)
     Int2,
-    /// Three unsigned 32-bit integers (equivalent to `[u32; 3]`)
+    /// Three signed 32-bit integers (equivalent to `[i32; 3]`)
     Int3,
-    /// Four unsigned 32-bit integers (equivalent to `[u32; 4]`)
+    /// Four signed 32-bit integers (equivalent to `[i32; 4]`)
     Int4,
+    /// One unsigned 32-bit integer (equivalent to `u32`)
+    UInt1,
+    /// Two unsigned 32-bit integers (equivalent to `[u32; 2]`)
+    UInt2,
+    /// Three unsigned 32-bit integers (equivalent to `[u32; 3]`)
+    UInt3,
+    /// Four unsigned 32-bit integers (equivalent to `[u32; 4]`)
+    UInt4,
+    /// GLSL `bool`, 32-bit wide (equivalent to `u32`, 0 is false)
+    Bool,
+    /// Sampler uniform, set to the index of an image in `Bindings::images` (equivalent to `i32`).
+    /// Make it an array to fill a GLSL sampler array
+    Sampler,
+    /// Two by two matrix of 32-bit floats
+    Mat2,
+    /// Three by three matrix of 32-bit floats
+    Mat3,
     /// Four by four matrix of 32-bit floats
     Mat4,
+    /// Matrix of 32-bit floats with two columns and three rows (GLSL `mat2x3`)
+    Mat2x3,
+    /// Matrix of 32-bit floats with two columns and four rows (GLSL `mat2x4`)
+    Mat2x4,
+    /// Matrix of 32-bit floats with three columns and two rows (GLSL `mat3x2`)
+    Mat3x2,
+    /// Matrix of 32-bit floats with three columns and four rows (GLSL `mat3x4`)
+    Mat3x4,
+    /// Matrix of 32-bit floats with four columns and two rows (GLSL `mat4x2`)
+    Mat4x2,
+    /// Matrix of 32-bit floats with four columns and three rows (GLSL `mat4x3`)
+    Mat4x3,
 }
 
 impl UniformType {
@@ -32,21 +63,184 @@ impl UniformType {
             UniformType::Int2 => 8,
             UniformType::Int3 => 12,
             UniformType::Int4 => 16,
+            UniformType::UInt1 => 4,
+            UniformType::UInt2 => 8,
+            UniformType::UInt3 => 12,
+            UniformType::UInt4 => 16,
+            UniformType::Bool => 4,
+            UniformType::Sampler => 4,
+            UniformType::Mat2 => 16,
+            UniformType::Mat3 => 36,
             UniformType::Mat4 => 64,
+            UniformType::Mat2x3 => 24,
+            UniformType::Mat2x4 => 32,
+            UniformType::Mat3x2 => 24,
+            UniformType::Mat3x4 => 48,
+            UniformType::Mat4x2 => 32,
+            UniformType::Mat4x3 => 48,
+        }
+    }
+
+    /// Maps a type reported by `glGetActiveUniform`, `None` for types uniforms can't be uploaded as
+    pub(crate) fn from_gl(gl_type: u32) -> Option<UniformType> {
+        let uniform_type = match gl_type {
+            glow::FLOAT => UniformType::Float1,
+            glow::FLOAT_VEC2 => UniformType::Float2,
+            glow::FLOAT_VEC3 => UniformType::Float3,
+            glow::FLOAT_VEC4 => UniformType::Float4,
+            glow::INT => UniformType::Int1,
+            glow::INT_VEC2 => UniformType::Int2,
+            glow::INT_VEC3 => UniformType::Int3,
+            glow::INT_VEC4 => UniformType::Int4,
+            glow::UNSIGNED_INT => UniformType::UInt1,
+            glow::UNSIGNED_INT_VEC2 => UniformType::UInt2,
+            glow::UNSIGNED_INT_VEC3 => UniformType::UInt3,
+            glow::UNSIGNED_INT_VEC4 => UniformType::UInt4,
+            glow::BOOL => UniformType::Bool,
+            glow::FLOAT_MAT2 => UniformType::Mat2,
+            glow::FLOAT_MAT3 => UniformType::Mat3,
+            glow::FLOAT_MAT4 => UniformType::Mat4,
+            glow::FLOAT_MAT2x3 => UniformType::Mat2x3,
+            glow::FLOAT_MAT2x4 => UniformType::Mat2x4,
+            glow::FLOAT_MAT3x2 => UniformType::Mat3x2,
+            glow::FLOAT_MAT3x4 => UniformType::Mat3x4,
+            glow::FLOAT_MAT4x2 => UniformType::Mat4x2,
+            glow::FLOAT_MAT4x3 => UniformType::Mat4x3,
+            glow::SAMPLER_2D
+            | glow::SAMPLER_3D
+            | glow::SAMPLER_CUBE
+            | glow::SAMPLER_2D_SHADOW
+            | glow::SAMPLER_2D_ARRAY
+            | glow::SAMPLER_2D_ARRAY_SHADOW
+            | glow::SAMPLER_CUBE_SHADOW
+            | glow::INT_SAMPLER_2D
+            | glow::INT_SAMPLER_3D
+            | glow::INT_SAMPLER_CUBE
+            | glow::INT_SAMPLER_2D_ARRAY
+            | glow::UNSIGNED_INT_SAMPLER_2D
+            | glow::UNSIGNED_INT_SAMPLER_3D
+            | glow::UNSIGNED_INT_SAMPLER_CUBE
+            | glow::UNSIGNED_INT_SAMPLER_2D_ARRAY => UniformType::Sampler,
+            _ => return None,
+        };
+        Some(uniform_type)
+    }
+
+    /// Number of columns for matrices, `None` for other types
+    fn columns(&self) -> Option<usize> {
+        match self {
+            UniformType::Mat2 | UniformType::Mat2x3 | UniformType::Mat2x4 => Some(2),
+            UniformType::Mat3 | UniformType::Mat3x2 | UniformType::Mat3x4 => Some(3),
+            UniformType::Mat4 | UniformType::Mat4x2 | UniformType::Mat4x3 => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Uploaded with entry points `glow` doesn't expose, see `RawGlFns::uniform_matrix`
+    pub(crate) fn is_non_square_matrix(&self) -> bool {
+        matches!(
+            self,
+            UniformType::Mat2x3
+                | UniformType::Mat2x4
+                | UniformType::Mat3x2
+                | UniformType::Mat3x4
+                | UniformType::Mat4x2
+                | UniformType::Mat4x3
+        )
+    }
+
+    /// Base alignment of a single value in std140 uniform blocks
+    fn std140_align(&self) -> usize {
+        match self {
+            UniformType::Float1 | UniformType::Int1 | UniformType::UInt1 => 4,
+            UniformType::Bool | UniformType::Sampler => 4,
+            UniformType::Float2 | UniformType::Int2 | UniformType::UInt2 => 8,
+            UniformType::Float3 | UniformType::Int3 | UniformType::UInt3 => 16,
+            UniformType::Float4 | UniformType::Int4 | UniformType::UInt4 => 16,
+            // Matrices are laid out as arrays of column vectors
+            _ => 16,
+        }
+    }
+
+    /// Size of a single value in std140 uniform blocks, where matrix columns are padded to vec4
+    fn std140_size(&self) -> usize {
+        match self.columns() {
+            Some(columns) => columns * 16,
+            None => self.size(),
         }
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct UniformDesc {
     pub(crate) name: String,
     pub(crate) uniform_type: UniformType,
     pub(crate) array_count: usize,
 }
 
+#[derive(Clone, Debug, Default)]
 pub struct UniformBlockLayout {
     pub uniforms: Vec<UniformDesc>,
 }
 
+impl UniformBlockLayout {
+    /// Offsets of the uniforms inside a `layout(std140)` uniform block with the same members
+    pub fn std140_offsets(&self) -> Vec<usize> {
+        self.std140_layout().0
+    }
+
+    /// Size of a `layout(std140)` uniform block with the same members, i.e. the smallest
+    /// uniform buffer it can be bound to
+    pub fn std140_size(&self) -> usize {
+        self.std140_layout().1
+    }
+
+    /// Checks that `T`, a `#[repr(C)]` struct with fields at `offsets` (e.g. taken with
+    /// `std::mem::offset_of!`), can be copied as is into a uniform buffer for this block.
+    pub fn check_std140<T>(&self, offsets: &[usize]) -> Result<(), Std140Error> {
+        let (expected_offsets, expected_size) = self.std140_layout();
+
+        if offsets.len() != expected_offsets.len() {
+            return Err(Std140Error::FieldCount {
+                expected: expected_offsets.len(),
+                found: offsets.len(),
+            });
+        }
+        for ((uniform, &expected), &found) in self.uniforms.iter().zip(&expected_offsets).zip(offsets) {
+            if expected != found {
+                return Err(Std140Error::Offset { uniform: uniform.name.clone(), expected, found });
+            }
+        }
+        let size = std::mem::size_of::<T>();
+        if size != expected_size {
+            return Err(Std140Error::Size { expected: expected_size, found: size });
+        }
+
+        Ok(())
+    }
+
+    fn std140_layout(&self) -> (Vec<usize>, usize) {
+        let mut offsets = Vec::with_capacity(self.uniforms.len());
+        let mut offset: usize = 0;
+
+        for uniform in &self.uniforms {
+            // Array elements are padded to vec4 each
+            let (align, size) = if uniform.array_count > 1 {
+                let stride = uniform.uniform_type.std140_size().next_multiple_of(16);
+                (uniform.uniform_type.std140_align().next_multiple_of(16), stride * uniform.array_count)
+            } else {
+                (uniform.uniform_type.std140_align(), uniform.uniform_type.std140_size())
+            };
+
+            offset = offset.next_multiple_of(align);
+            offsets.push(offset);
+            offset += size;
+        }
+
+        (offsets, offset.next_multiple_of(16))
+    }
+}
+
 impl UniformDesc {
     pub fn new(name: &str, uniform_type: UniformType) -> UniformDesc {
         UniformDesc {
@@ -168,6 +362,20 @@ impl VertexFormat {
             VertexFormat::Mat4 => glow::FLOAT,
         }
     }
+
+    /// Maps a type reported by `glGetActiveAttrib` to the float format feeding it,
+    /// `None` for integer and other types
+    pub(crate) fn from_gl(gl_type: u32) -> Option<VertexFormat> {
+        let format = match gl_type {
+            glow::FLOAT => VertexFormat::Float1,
+            glow::FLOAT_VEC2 => VertexFormat::Float2,
+            glow::FLOAT_VEC3 => VertexFormat::Float3,
+            glow::FLOAT_VEC4 => VertexFormat::Float4,
+            glow::FLOAT_MAT4 => VertexFormat::Mat4,
+            _ => return None,
+        };
+        Some(format)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -224,6 +432,34 @@ impl VertexAttribute {
     }
 }
 
+/// Struct passed to [`crate::Context::apply_uniforms()`] which knows the uniforms it holds.
+///
+/// With the `derive` feature it is implemented by `#[derive(Uniforms)]`, which also checks at
+/// compile time that fields follow each other without padding, as `apply_uniforms` expects.
+pub trait Uniforms {
+    fn uniform_layout() -> UniformBlockLayout;
+}
+
+/// Vertex struct which knows the attributes it holds.
+///
+/// With the `derive` feature it is implemented by `#[derive(VertexLayout)]`, which also checks at
+/// compile time that fields follow each other without padding.
+pub trait VertexLayout {
+    /// Attributes read from `Bindings::vertex_buffers[buffer_index]`
+    fn attributes(buffer_index: usize) -> Vec<VertexAttribute>;
+
+    /// Per vertex layout with the stride of the struct, trailing padding included
+    fn buffer_layout() -> BufferLayout
+    where
+        Self: Sized,
+    {
+        BufferLayout {
+            stride: std::mem::size_of::<Self>() as i32,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PipelineLayout {
     pub buffers: &'static [BufferLayout],
@@ -453,6 +689,12 @@ impl From<Comparison> for u32 {
 pub enum PrimitiveType {
     Triangles,
     Lines,
+    /// Point size is controlled by `gl_PointSize` written in vertex shader
+    Points,
+    LineStrip,
+    LineLoop,
+    TriangleStrip,
+    TriangleFan,
 }
 
 impl From<PrimitiveType> for u32 {
@@ -460,11 +702,16 @@ impl From<PrimitiveType> for u32 {
         match primitive_type {
             PrimitiveType::Triangles => glow::TRIANGLES,
             PrimitiveType::Lines => glow::LINES,
+            PrimitiveType::Points => glow::POINTS,
+            PrimitiveType::LineStrip => glow::LINE_STRIP,
+            PrimitiveType::LineLoop => glow::LINE_LOOP,
+            PrimitiveType::TriangleStrip => glow::TRIANGLE_STRIP,
+            PrimitiveType::TriangleFan => glow::TRIANGLE_FAN,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum IndexType {
     Byte,
     Short,
@@ -498,12 +745,23 @@ impl IndexType {
             IndexType::Int => 4,
         }
     }
+
+    /// Largest index of this type, where fixed index primitive restart happens
+    pub fn max_index(self) -> u32 {
+        match self {
+            IndexType::Byte => 0xFF,
+            IndexType::Short => 0xFFFF,
+            IndexType::Int => 0xFFFF_FFFF,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BufferType {
     VertexBuffer,
     IndexBuffer,
+    /// Storage of a uniform block, see [`crate::ShaderMeta::uniform_blocks`]
+    UniformBuffer,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -511,4 +769,205 @@ pub enum Usage {
     Immutable,
     Dynamic,
     Stream,
-}
\ No newline at end of file
+}
+
+/// Buffers copied by [`crate::Context::blit()`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlitMask {
+    pub color: bool,
+    pub depth: bool,
+    pub stencil: bool,
+}
+
+impl BlitMask {
+    pub const COLOR: BlitMask = BlitMask { color: true, depth: false, stencil: false };
+    pub const DEPTH_STENCIL: BlitMask = BlitMask { color: false, depth: true, stencil: true };
+    pub const ALL: BlitMask = BlitMask { color: true, depth: true, stencil: true };
+
+    pub(crate) fn gl_bits(self) -> u32 {
+        let mut bits = 0;
+        if self.color {
+            bits |= glow::COLOR_BUFFER_BIT;
+        }
+        if self.depth {
+            bits |= glow::DEPTH_BUFFER_BIT;
+        }
+        if self.stencil {
+            bits |= glow::STENCIL_BUFFER_BIT;
+        }
+        bits
+    }
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use glow::HasContext;
+    use crate::*;
+
+    const VERTEX: &str = r#"#version 330
+    in vec2 pos;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+    }"#;
+
+    /// Every uniform contributes to the output, so none of them gets optimized out
+    const FRAGMENT: &str = r#"#version 330
+    uniform mat2 m2;
+    uniform mat3 m3;
+    uniform mat2x3 m23;
+    uniform mat2x4 m24;
+    uniform mat3x2 m32;
+    uniform mat3x4 m34;
+    uniform mat4x2 m42;
+    uniform mat4x3 m43;
+    uniform uint u1;
+    uniform uvec2 u2;
+    uniform uvec3 u3;
+    uniform uvec4 u4;
+    uniform bool b;
+    uniform sampler2D tex[2];
+    out vec4 color;
+
+    void main() {
+        float sum = m2[1].y + m3[2].z + m23[1].z + m24[1].w + m32[2].y + m34[2].w + m42[3].y + m43[3].z;
+        uvec4 u = uvec4(u1) + uvec4(u2, u2) + uvec4(u3, 0u) + u4;
+        color = vec4(sum, float(u.x + u.w), b ? 1.0 : 0.0, 1.0) + texture(tex[0], vec2(0)) + texture(tex[1], vec2(0));
+    }"#;
+
+    const UNIFORMS: [(&str, UniformType); 13] = [
+        ("m2", UniformType::Mat2),
+        ("m3", UniformType::Mat3),
+        ("m23", UniformType::Mat2x3),
+        ("m24", UniformType::Mat2x4),
+        ("m32", UniformType::Mat3x2),
+        ("m34", UniformType::Mat3x4),
+        ("m42", UniformType::Mat4x2),
+        ("m43", UniformType::Mat4x3),
+        ("u1", UniformType::UInt1),
+        ("u2", UniformType::UInt2),
+        ("u3", UniformType::UInt3),
+        ("u4", UniformType::UInt4),
+        ("b", UniformType::Bool),
+    ];
+
+    fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex[0]".to_string(), "tex[1]".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: UNIFORMS.iter().map(|&(name, uniform_type)| UniformDesc::new(name, uniform_type)).collect(),
+            },
+            uniform_blocks: vec![],
+        }
+    }
+
+    /// Distinct value for every scalar of the uniforms, as `apply_uniforms` reads them
+    fn words() -> Vec<u32> {
+        let mut words = vec![];
+        for (i, &(_, uniform_type)) in UNIFORMS.iter().enumerate() {
+            let count = uniform_type.size() / 4;
+            words.extend((0..count).map(|j| match uniform_type {
+                UniformType::Bool => 1,
+                UniformType::UInt1 | UniformType::UInt2 | UniformType::UInt3 | UniformType::UInt4 => {
+                    (i * 100 + j) as u32
+                }
+                _ => ((i * 100 + j) as f32 + 0.5).to_bits(),
+            }));
+        }
+        words
+    }
+
+    #[test]
+    fn uniforms_round_trip() {
+        let mut ctx = match Context::new_headless(4, 4) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                eprintln!("skipping, no headless context: {}", e);
+                return;
+            }
+        };
+
+        let shader = Shader::new(&mut ctx, VERTEX, FRAGMENT, meta()).unwrap();
+        let pipeline = Pipeline::new(
+            &mut ctx,
+            &[BufferLayout::default()],
+            &[VertexAttribute::new("pos", VertexFormat::Float2)],
+            shader,
+        );
+        let vertices: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+        let images = (0..2)
+            .map(|_| Texture::from_rgba8(&mut ctx, 1, 1, 1, &[0; 4], TextureKind::Texture2D))
+            .collect();
+        let bindings = Bindings {
+            vertex_buffers: vec![Buffer::immutable(&mut ctx, BufferType::VertexBuffer, &vertices)],
+            index_buffer: None,
+            images,
+            uniform_buffers: vec![],
+        };
+
+        let words = words();
+        ctx.begin_default_pass(PassAction::Nothing);
+        ctx.apply_pipeline(&pipeline);
+        ctx.apply_bindings(&bindings);
+        ctx.apply_uniforms_from_bytes(words.as_ptr() as *const u8, words.len() * 4);
+        ctx.draw_arrays(0, 3, 1);
+        ctx.end_render_pass();
+
+        let gl = &ctx.glow_ctx.0.gl;
+        let program = ctx.shader_internal(shader).program;
+        let mut offset = 0;
+        for &(name, uniform_type) in UNIFORMS.iter() {
+            let count = uniform_type.size() / 4;
+            let expected = &words[offset..offset + count];
+            offset += count;
+
+            unsafe {
+                let location = gl.get_uniform_location(program, name).unwrap();
+                let actual: Vec<u32> = match uniform_type {
+                    // Unsigned values this small read back the same through glGetUniformiv
+                    UniformType::UInt1 | UniformType::UInt2 | UniformType::UInt3 | UniformType::UInt4 | UniformType::Bool => {
+                        let mut values = vec![0; count];
+                        gl.get_uniform_i32(program, &location, &mut values);
+                        values.into_iter().map(|value| value as u32).collect()
+                    }
+                    _ => {
+                        let mut values = vec![0.0; count];
+                        gl.get_uniform_f32(program, &location, &mut values);
+                        values.into_iter().map(f32::to_bits).collect()
+                    }
+                };
+                assert_eq!(actual, expected, "{} ({:?})", name, uniform_type);
+            }
+        }
+
+        for i in 0..2 {
+            unsafe {
+                let location = gl.get_uniform_location(program, &format!("tex[{}]", i)).unwrap();
+                let mut unit = [-1];
+                gl.get_uniform_i32(program, &location, &mut unit);
+                assert_eq!(unit, [i]);
+            }
+        }
+    }
+
+    #[test]
+    fn non_square_matrices_need_entry_points() {
+        let egl = match crate::headless::HeadlessEgl::new() {
+            Ok(egl) => egl,
+            Err(e) => {
+                eprintln!("skipping, no headless context: {}", e);
+                return;
+            }
+        };
+        // Contexts created by `Context::from_glow` lack the raw entry points
+        let gl = unsafe { glow::Context::from_loader_function(|s| egl.get_proc_address(s)) };
+        let mut ctx = Context::from_glow(gl, 4, 4);
+
+        match Shader::new(&mut ctx, VERTEX, FRAGMENT, meta()) {
+            Err(ShaderError::UniformTypeUnavailable { name, uniform_type }) => {
+                assert_eq!((name.as_str(), uniform_type), ("m23", UniformType::Mat2x3));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::error::Error;
+use std::fmt::Display;
+use crate::{Context, IndexType};
+use crate::command_list_impl::BindingsSource;
+use crate::slot_map::stale_handle;
+
+/// Mismatch between a call and the state it relies on, found by the validation layer,
+/// see [`Context::set_validation()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Draw or `apply_*` call made before any [`Context::apply_pipeline()`]
+    NoPipeline,
+    /// Draw call made without bindings applied after the current pipeline
+    NoBindings,
+    /// Pipeline reads the attribute at `location` from a vertex buffer the bindings don't have
+    MissingVertexBuffer { location: u32, buffer_index: usize, vertex_buffers: usize },
+    /// Bindings have fewer images than the shader samples
+    MissingImages { expected: usize, found: usize },
+    /// Bindings have fewer uniform buffers than the shader has uniform blocks
+    MissingUniformBuffers { expected: usize, found: usize },
+    /// Uniform buffer is smaller than the block it is bound to, sizes are in bytes
+    UniformBufferTooSmall { block: usize, required: usize, size: usize },
+    /// Indexed draw with no index buffer in bindings
+    MissingIndexBuffer,
+    /// Negative first element, element count or instance count
+    NegativeRange { first: i32, count: i32, instances: i32 },
+    /// Indexed draw reads past the end of the index buffer, sizes are in bytes
+    IndexRangeOutOfBounds { base_element: i32, num_elements: i32, required: usize, size: usize },
+    /// Draw reads the attribute at `location` past the end of its vertex buffer, sizes are in bytes
+    VertexRangeOutOfBounds { location: u32, buffer_index: usize, required: usize, size: usize },
+    /// Uniforms struct size differs from the uniforms declared in `ShaderMeta`, in bytes
+    UniformsSizeMismatch { expected: usize, found: usize },
+    /// Indexed draw with a restart index GL won't restart at: without `glPrimitiveRestartIndex`
+    /// it has to be the maximum value of the bound index type, see [`Context::set_primitive_restart()`]
+    PrimitiveRestartMismatch { restart_index: u32, index_type: IndexType },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// What the validation layer knows about the applied bindings.
+#[derive(Default)]
+pub(crate) struct ValidationState {
+    pub(crate) enabled: bool,
+    /// Bindings were applied after the last `apply_pipeline`
+    bindings_applied: bool,
+    vertex_buffer_sizes: Vec<usize>,
+    index_buffer_size: Option<usize>,
+}
+
+impl ValidationState {
+    pub(crate) fn pipeline_applied(&mut self) {
+        self.bindings_applied = false;
+    }
+
+    /// Forgets applied bindings, which go stale while validation is off
+    fn reset(&mut self) {
+        self.bindings_applied = false;
+        self.vertex_buffer_sizes.clear();
+        self.index_buffer_size = None;
+    }
+
+    pub(crate) fn bindings_applied(&mut self, bindings: &impl BindingsSource) {
+        self.bindings_applied = true;
+        self.vertex_buffer_sizes.clear();
+        self.vertex_buffer_sizes
+            .extend((0..bindings.vertex_buffer_count()).map(|index| bindings.vertex_buffer(index).1));
+        self.index_buffer_size = bindings.index_buffer().map(|(_, size, _)| size);
+    }
+}
+
+impl Context {
+    /// Turns checks of bindings, uniforms and draw ranges against the current pipeline on or off.
+    ///
+    /// With validation on, failed checks panic with a [`ValidationError`] before any GL call
+    /// is made, instead of panicking somewhere inside or reading out of bounds memory.
+    /// Off by default, as the checks cost some CPU time on every call.
+    ///
+    /// Applied pipelines and bindings are only tracked while validation is on, so it should be
+    /// enabled before applying them. Turning it on or off forgets bindings applied before, so
+    /// draws report [`ValidationError::NoBindings`] until bindings are applied with validation on.
+    pub fn set_validation(&mut self, enabled: bool) {
+        if enabled != self.validation.enabled {
+            self.validation.reset();
+        }
+        self.validation.enabled = enabled;
+    }
+
+    pub fn validation_enabled(&self) -> bool {
+        self.validation.enabled
+    }
+
+    /// Runs the checks [`Context::draw()`] does with validation on, which the bindings
+    /// have to be applied with.
+    pub fn validate_draw(
+        &self,
+        base_element: i32,
+        num_elements: i32,
+        num_instances: i32,
+    ) -> Result<(), ValidationError> {
+        if base_element < 0 || num_elements < 0 || num_instances < 0 {
+            return Err(ValidationError::NegativeRange {
+                first: base_element,
+                count: num_elements,
+                instances: num_instances,
+            });
+        }
+        self.validate_vertex_ranges(0, num_instances)?;
+
+        let (size, index_type) = match (self.validation.index_buffer_size, self.cache.index_type) {
+            (Some(size), Some(index_type)) => (size, index_type),
+            _ => return Err(ValidationError::MissingIndexBuffer),
+        };
+        let required = (base_element as usize + num_elements as usize) * index_type.size() as usize;
+        if required > size {
+            return Err(ValidationError::IndexRangeOutOfBounds { base_element, num_elements, required, size });
+        }
+        if let Some(restart_index) = self.cache.primitive_restart {
+            if self.fixed_restart_index() && restart_index != index_type.max_index() {
+                return Err(ValidationError::PrimitiveRestartMismatch { restart_index, index_type });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the checks [`Context::draw_arrays()`] does with validation on, which the bindings
+    /// have to be applied with.
+    pub fn validate_draw_arrays(
+        &self,
+        first_vertex: i32,
+        num_vertices: i32,
+        num_instances: i32,
+    ) -> Result<(), ValidationError> {
+        if first_vertex < 0 || num_vertices < 0 || num_instances < 0 {
+            return Err(ValidationError::NegativeRange {
+                first: first_vertex,
+                count: num_vertices,
+                instances: num_instances,
+            });
+        }
+        self.validate_vertex_ranges(first_vertex.saturating_add(num_vertices), num_instances)
+    }
+
+    /// Checks that `num_vertices` vertices and `num_instances` instances of every attribute
+    /// fit their buffers, per vertex attributes of indexed draws are left unchecked with 0 vertices
+    fn validate_vertex_ranges(&self, num_vertices: i32, num_instances: i32) -> Result<(), ValidationError> {
+        let cur_pipeline = self.cache.cur_pipeline.ok_or(ValidationError::NoPipeline)?;
+        if !self.validation.bindings_applied {
+            return Err(ValidationError::NoBindings);
+        }
+        let pip = self.pipelines.get(cur_pipeline.0).unwrap_or_else(|| stale_handle(cur_pipeline));
+
+        for attribute in pip.layout.iter().flatten() {
+            // Both are checked to be non-negative
+            let count = if attribute.divisor == 0 {
+                num_vertices as u32
+            } else {
+                (num_instances as u32).div_ceil(attribute.divisor as u32)
+            };
+            if count == 0 {
+                continue;
+            }
+
+            let size = *self.validation.vertex_buffer_sizes.get(attribute.buffer_index).ok_or(
+                ValidationError::MissingVertexBuffer {
+                    location: attribute.attr_loc,
+                    buffer_index: attribute.buffer_index,
+                    vertex_buffers: self.validation.vertex_buffer_sizes.len(),
+                }
+            )?;
+            let required = attribute.offset as usize
+                + attribute.stride as usize * (count as usize - 1)
+                + attribute.byte_len as usize;
+            if required > size {
+                return Err(ValidationError::VertexRangeOutOfBounds {
+                    location: attribute.attr_loc,
+                    buffer_index: attribute.buffer_index,
+                    required,
+                    size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate_bindings(&self, bindings: &impl BindingsSource) -> Result<(), ValidationError> {
+        let cur_pipeline = self.cache.cur_pipeline.ok_or(ValidationError::NoPipeline)?;
+        let pip = self.pipelines.get(cur_pipeline.0).unwrap_or_else(|| stale_handle(cur_pipeline));
+        let shader = self.shaders.get(pip.shader.0).unwrap_or_else(|| stale_handle(pip.shader));
+
+        if bindings.image_count() < shader.images.len() {
+            return Err(ValidationError::MissingImages {
+                expected: shader.images.len(),
+                found: bindings.image_count(),
+            });
+        }
+
+        if bindings.uniform_buffer_count() < shader.uniform_blocks.len() {
+            return Err(ValidationError::MissingUniformBuffers {
+                expected: shader.uniform_blocks.len(),
+                found: bindings.uniform_buffer_count(),
+            });
+        }
+        for (block, shader_block) in shader.uniform_blocks.iter().enumerate() {
+            let (_, size) = bindings.uniform_buffer(block);
+            if size < shader_block.size {
+                return Err(ValidationError::UniformBufferTooSmall { block, required: shader_block.size, size });
+            }
+        }
+
+        for attribute in pip.layout.iter().flatten() {
+            if attribute.buffer_index >= bindings.vertex_buffer_count() {
+                return Err(ValidationError::MissingVertexBuffer {
+                    location: attribute.attr_loc,
+                    buffer_index: attribute.buffer_index,
+                    vertex_buffers: bindings.vertex_buffer_count(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate_uniforms(&self, size: usize) -> Result<(), ValidationError> {
+        let cur_pipeline = self.cache.cur_pipeline.ok_or(ValidationError::NoPipeline)?;
+        let shader = self.shader_internal(self.pipeline_internal(cur_pipeline).shader);
+
+        let expected = shader
+            .uniforms
+            .iter()
+            .map(|uniform| uniform.uniform_type.size() * uniform.array_count as usize)
+            .sum();
+        if size != expected {
+            return Err(ValidationError::UniformsSizeMismatch { expected, found: size });
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::error::Error;
+use std::fmt::Display;
 use sdl2::event::WindowEvent;
 use sdl2::EventPump;
 use sdl2::keyboard::{Mod};
@@ -177,6 +179,11 @@ pub struct Conf {
     pub sample_buffers: u8,
     /// Determines if the application user can resize the window
     pub window_resizable: bool,
+    /// Requests a debug GL context, which makes drivers report much more through
+    /// [`Context::enable_debug_output()`]
+    ///
+    /// Default: false
+    pub debug_context: bool,
 }
 
 impl Default for Conf {
@@ -190,18 +197,46 @@ impl Default for Conf {
             sample_count: 1,
             sample_buffers: 1,
             window_resizable: true,
+            debug_context: false,
         }
     }
 }
 
-fn make_ctx_and_other_goodies(conf: &Conf) -> (Context, WindowContext, EventPump, GLContext) {
-    let sdl = sdl2::init().unwrap();
-    let video = sdl.video().unwrap();
+#[derive(Clone, Debug)]
+pub enum WindowError {
+    /// SDL or one of its subsystems failed to initialize
+    Init(String),
+    /// Window can't be created with the given `Conf`
+    WindowCreation(String),
+    /// OpenGL 3.2 core context is not available for the window
+    ContextCreation(String),
+}
+
+impl Display for WindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self) // Display the same way as Debug
+    }
+}
+
+impl Error for WindowError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+fn make_ctx_and_other_goodies(
+    conf: &Conf
+) -> Result<(Context, WindowContext, EventPump, GLContext), WindowError> {
+    let sdl = sdl2::init().map_err(WindowError::Init)?;
+    let video = sdl.video().map_err(WindowError::Init)?;
     let gl_attr = video.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(3, 2);
     gl_attr.set_multisample_buffers(conf.sample_buffers);
     gl_attr.set_multisample_samples(conf.sample_count);
+    if conf.debug_context {
+        gl_attr.set_context_flags().debug().set();
+    }
 
     let mut window_builder = video.window(
         &conf.window_title,
@@ -223,9 +258,11 @@ fn make_ctx_and_other_goodies(conf: &Conf) -> (Context, WindowContext, EventPump
         window_builder.fullscreen();
     }
 
-    let window = window_builder.build().unwrap();
+    let window = window_builder
+        .build()
+        .map_err(|e| WindowError::WindowCreation(e.to_string()))?;
 
-    let gl_context = window.gl_create_context().unwrap();
+    let gl_context = window.gl_create_context().map_err(WindowError::ContextCreation)?;
 
     let mut ctx = Context::new_from_sdl2(&video, conf.window_width, conf.window_height);
 
@@ -236,41 +273,61 @@ fn make_ctx_and_other_goodies(conf: &Conf) -> (Context, WindowContext, EventPump
         drawable_size.1 as f32 / conf.window_height as f32
     );
 
-    let event_loop = sdl.event_pump().unwrap();
+    let event_loop = sdl.event_pump().map_err(WindowError::Init)?;
+    let event_subsystem = sdl.event().map_err(WindowError::Init)?;
 
-    (
+    Ok((
         ctx,
         WindowContext(
             window,
             video,
             sdl.mouse(),
-            sdl.event().unwrap()
+            event_subsystem
         ),
         event_loop,
         gl_context
-    )
+    ))
 }
 
 pub fn start<THandler: SimpleEventHandler>(conf: Conf) {
+    try_start::<THandler>(conf).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Same as [`start()`], but reports window and GL context creation failures instead of panicking.
+pub fn try_start<THandler: SimpleEventHandler>(conf: Conf) -> Result<(), WindowError> {
     let (mut ctx, mut window_context, mut events_loop, _gl_context) = {
-        make_ctx_and_other_goodies(&conf)
+        make_ctx_and_other_goodies(&conf)?
     };
 
     let mut handler = THandler::make(&mut ctx, &mut window_context);
 
     start_main_loop(&mut ctx, &mut window_context, &mut events_loop, &mut handler);
+
+    Ok(())
 }
 
 pub fn start_parametrized<THandler, TParameter>(conf: Conf, extra_parameter: TParameter)
 where THandler: ParametrizedEventHandler<TParameter>
+{
+    try_start_parametrized::<THandler, TParameter>(conf, extra_parameter)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_start_parametrized<THandler, TParameter>(
+    conf: Conf,
+    extra_parameter: TParameter
+) -> Result<(), WindowError>
+where THandler: ParametrizedEventHandler<TParameter>
 {
     let (mut ctx, mut window_context, mut events_loop, _gl_context) = {
-        make_ctx_and_other_goodies(&conf)
+        make_ctx_and_other_goodies(&conf)?
     };
 
     let mut handler = THandler::make(&mut ctx, &mut window_context, extra_parameter);
 
     start_main_loop(&mut ctx, &mut window_context, &mut events_loop, &mut handler);
+
+    Ok(())
 }
 
 fn start_main_loop<THandler: EventHandler>(